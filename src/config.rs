@@ -0,0 +1,531 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, Deserializer, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any,
+};
+use serde_json::{Map, Value};
+
+use crate::{Directories, Environment, Error, HostEnv};
+
+type ParseError = Box<dyn std::error::Error + Send + Sync>;
+type Parser = fn(&str) -> Result<Value, ParseError>;
+
+/// Supported file extensions, each with the parser for its format. Only formats of enabled
+/// features are included, in the order they are loaded within a single directory.
+const FORMATS: &[(&str, Parser)] = &[
+    #[cfg(feature = "toml")]
+    ("toml", parse_toml),
+    ("json", parse_json),
+    #[cfg(feature = "yaml")]
+    ("yaml", parse_yaml),
+    #[cfg(feature = "yaml")]
+    ("yml", parse_yaml),
+];
+
+/// Loader for a typed configuration, that is merged from all config files in the
+/// [`config_dirs`](Directories::config_dirs) and optionally environment variables.
+///
+/// Files are named after the configured base name with an extension for each enabled format
+/// feature. JSON is the format that all layers are merged in, so it is always supported as part of
+/// the `serde` feature, which `json` is an alias for:
+///
+/// | Feature          | Extensions      |
+/// | ---------------- | --------------- |
+/// | `toml`           | `.toml`         |
+/// | `serde` / `json` | `.json`         |
+/// | `yaml`           | `.yaml`, `.yml` |
+///
+/// The layers are merged from lowest to highest priority, meaning that vendor defaults are
+/// overridden by the system-wide configuration, which is in turn overridden by the user's own
+/// configuration. Tables are merged recursively, while all other values, including lists, are
+/// replaced as a whole. If a directory contains files of several formats, they are merged in the
+/// order of the table above.
+///
+/// Environment variables are applied last, if a prefix was set with
+/// [`ConfigLoader::env_prefix`]. See there for the naming scheme.
+///
+/// As environment variables can only hold strings, string values are converted to the type that
+/// is requested during deserialization, like a number or a list in JSON notation.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(feature = "toml")] {
+/// use serde::Deserialize;
+/// use tempfile::TempDir;
+/// use unidirs::{ConfigLoader, ConfigSource, Directories, MockEnv, ServiceDirs, Utf8Path};
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let temp = TempDir::new().unwrap();
+/// let dirs = ServiceDirs::builder("example", "app")
+///     .prefix(Utf8Path::from_path(temp.path()).unwrap())
+///     .build();
+///
+/// let (user, vendor) = (dirs.config_dir(), &dirs.system_config_dirs()[0]);
+/// std::fs::create_dir_all(user).unwrap();
+/// std::fs::create_dir_all(vendor).unwrap();
+/// std::fs::write(vendor.join("config.toml"), "host = 'localhost'\nport = 80").unwrap();
+/// std::fs::write(user.join("config.toml"), "port = 8080").unwrap();
+///
+/// let config = ConfigLoader::new(&dirs, "config")
+///     .env_prefix("APP")
+///     .environment(MockEnv::new().with_var("APP__HOST", "example.com"))
+///     .load::<Config>()
+///     .unwrap();
+///
+/// assert_eq!("example.com", config.value().host);
+/// assert_eq!(8080, config.value().port);
+/// assert_eq!(
+///     Some(&ConfigSource::File(user.join("config.toml"))),
+///     config.source("port"),
+/// );
+/// assert_eq!(Some(&ConfigSource::Env("APP__HOST".to_owned())), config.source("host"));
+/// # }
+/// ```
+pub struct ConfigLoader<'a> {
+    dirs: &'a dyn Directories,
+    name: String,
+    env_prefix: Option<String>,
+    env: Box<dyn Environment>,
+}
+
+impl<'a> ConfigLoader<'a> {
+    /// Create a new loader for config files with the given base name, that are searched in the
+    /// config directories of `dirs`.
+    #[must_use]
+    pub fn new(dirs: &'a dyn Directories, name: impl Into<String>) -> Self {
+        Self {
+            dirs,
+            name: name.into(),
+            env_prefix: None,
+            env: Box::new(HostEnv),
+        }
+    }
+
+    /// Enable overrides from environment variables that start with the given prefix, followed by
+    /// a double underscore. The rest of the name is the key of the value in lowercase, with nested
+    /// keys separated by a double underscore as well. For example, with the prefix `APP` the
+    /// variable `APP__SERVER__PORT` sets the `port` value in the `server` table.
+    ///
+    /// The double underscore keeps other variables of the application apart from the
+    /// configuration, like `APP_SERVICE` or `APP_CACHE_DIR` that are used by the
+    /// [`SimpleBuilder`](crate::SimpleBuilder).
+    ///
+    /// Values are kept as strings and only converted when the requested type expects something
+    /// else. Therefore, `APP__PORT=8080` fills a number, while `APP__TOKEN=123456` fills a string
+    /// as is. Lists and tables are given in JSON notation, like `APP__HOSTS=["a","b"]`. Variables
+    /// with a name or value that is not valid UTF-8 are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    /// use unidirs::{ConfigLoader, LocalDirs, MockEnv};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     token: String,
+    ///     port: u16,
+    ///     debug: bool,
+    ///     hosts: Vec<String>,
+    ///     timeout: Option<u32>,
+    /// }
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_var("APP__TOKEN", "123456")
+    ///     .with_var("APP__PORT", "8080")
+    ///     .with_var("APP__DEBUG", "true")
+    ///     .with_var("APP__HOSTS", r#"["a", "b"]"#)
+    ///     .with_var("APP__TIMEOUT", "30");
+    /// let dirs = LocalDirs::new_at("/nonexistent");
+    /// let config = ConfigLoader::new(&dirs, "config")
+    ///     .env_prefix("APP")
+    ///     .environment(env)
+    ///     .load::<Config>()
+    ///     .unwrap()
+    ///     .into_value();
+    ///
+    /// assert_eq!("123456", config.token);
+    /// assert_eq!(8080, config.port);
+    /// assert!(config.debug);
+    /// assert_eq!(vec!["a", "b"], config.hosts);
+    /// assert_eq!(Some(30), config.timeout);
+    /// ```
+    #[must_use]
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Use the given environment to read the override variables from, instead of the process
+    /// environment.
+    #[must_use]
+    pub fn environment(mut self, env: impl Environment + 'static) -> Self {
+        self.env = Box::new(env);
+        self
+    }
+
+    /// Load all layers and deserialize the merged result into the requested type.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the config files can't be read or parsed, or the merged values don't match
+    /// the structure of the type.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<LoadedConfig<T>, Error> {
+        let mut merged = Map::new();
+        let mut files = Vec::new();
+        let mut sources = BTreeMap::new();
+
+        for dir in self.dirs.config_dirs().into_iter().rev() {
+            for (extension, parse) in FORMATS {
+                let path = dir.join(format!("{}.{extension}", self.name));
+                if !path.is_file() {
+                    continue;
+                }
+
+                let layer = read(&path, *parse)?;
+                let source = ConfigSource::File(path.clone());
+                merge(&mut merged, layer, "", &source, &mut sources);
+                files.push(path);
+            }
+        }
+
+        if let Some(prefix) = &self.env_prefix {
+            for (name, layer) in env_layers(&*self.env, prefix) {
+                merge(
+                    &mut merged,
+                    layer,
+                    "",
+                    &ConfigSource::Env(name),
+                    &mut sources,
+                );
+            }
+        }
+
+        let value = T::deserialize(Lenient(Value::Object(merged)))
+            .map_err(|e| Error::InvalidConfig(Box::new(e)))?;
+
+        Ok(LoadedConfig {
+            value,
+            files,
+            sources,
+        })
+    }
+}
+
+/// A configuration that was loaded by a [`ConfigLoader`], together with the origin of each value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedConfig<T> {
+    value: T,
+    files: Vec<Utf8PathBuf>,
+    sources: BTreeMap<String, ConfigSource>,
+}
+
+impl<T> LoadedConfig<T> {
+    /// The deserialized configuration.
+    #[must_use]
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Take the deserialized configuration, discarding the origins of the values.
+    #[must_use]
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// All config files that were loaded, from lowest to highest priority.
+    #[must_use]
+    pub fn files(&self) -> &[Utf8PathBuf] {
+        &self.files
+    }
+
+    /// The origin of the value with the given key, where nested keys are separated by a dot (for
+    /// example `server.port`). Only values that are not tables themselves have an origin.
+    #[must_use]
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        self.sources.get(key)
+    }
+
+    /// The origins of all values, sorted by their key.
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &ConfigSource)> {
+        self.sources
+            .iter()
+            .map(|(key, source)| (key.as_str(), source))
+    }
+}
+
+/// The origin of a single configuration value.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum ConfigSource {
+    /// The value was defined in the config file at this path.
+    File(Utf8PathBuf),
+    /// The value was defined in the environment variable with this name.
+    Env(String),
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "file `{path}`"),
+            Self::Env(name) => write!(f, "environment variable `{name}`"),
+        }
+    }
+}
+
+fn read(path: &Utf8Path, parse: Parser) -> Result<Map<String, Value>, Error> {
+    let content = fs::read_to_string(path).map_err(|source| Error::ReadConfig {
+        path: path.to_owned(),
+        source,
+    })?;
+    let parse_err = |source| Error::ParseConfig {
+        path: path.to_owned(),
+        source,
+    };
+
+    match parse(&content).map_err(parse_err)? {
+        Value::Object(map) => Ok(map),
+        // An empty YAML document has no value at all.
+        Value::Null => Ok(Map::new()),
+        _ => Err(parse_err("expected a table of values".into())),
+    }
+}
+
+/// Merge the layer into the target, recording the source of each value that is set by it.
+fn merge(
+    target: &mut Map<String, Value>,
+    layer: Map<String, Value>,
+    prefix: &str,
+    source: &ConfigSource,
+    sources: &mut BTreeMap<String, ConfigSource>,
+) {
+    for (key, value) in layer {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        if let Value::Object(value) = value {
+            let entry = target
+                .entry(key)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+                sources.remove(&path);
+            }
+            if let Value::Object(entry) = entry {
+                merge(entry, value, &path, source, sources);
+            }
+        } else {
+            // A replaced table takes the origins of all its nested values along.
+            let nested = format!("{path}.");
+            sources.retain(|key, _| !key.starts_with(&nested));
+            sources.insert(path, source.clone());
+            target.insert(key, value);
+        }
+    }
+}
+
+/// Convert all environment variables with the given prefix into single-value layers, sorted by
+/// their name.
+fn env_layers(env: &dyn Environment, prefix: &str) -> BTreeMap<String, Map<String, Value>> {
+    let prefix = format!("{prefix}__");
+
+    env.vars_os()
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let (name, value) = (name.into_string().ok()?, value.into_string().ok()?);
+            let keys = name
+                .strip_prefix(&prefix)?
+                .split("__")
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>();
+            if keys.iter().any(String::is_empty) {
+                return None;
+            }
+            let (last, parents) = keys.split_last()?;
+            let mut layer = Map::from_iter([(last.clone(), Value::String(value))]);
+            for key in parents.iter().rev() {
+                layer = Map::from_iter([(key.clone(), Value::Object(layer))]);
+            }
+
+            Some((name, layer))
+        })
+        .collect()
+}
+
+/// Deserializer for the merged values, that converts strings into the requested type, if they
+/// can be parsed as JSON value of that type. Any other value is deserialized as is.
+struct Lenient(Value);
+
+impl Lenient {
+    /// Parse a string value, if the result is of the expected kind.
+    fn parse(self, kind: fn(&Value) -> bool) -> Value {
+        match self.0 {
+            Value::String(s) => match serde_json::from_str(&s) {
+                Ok(value) if kind(&value) => value,
+                _ => Value::String(s),
+            },
+            value => value,
+        }
+    }
+}
+
+/// Deserialize a number, converting it from a string first.
+macro_rules! deserialize_number {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.parse(Value::is_number).$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Array(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter().map(Lenient));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Object(map) => {
+                let mut map = MapDeserializer::new(
+                    map.into_iter()
+                        .map(|(key, value)| (Lenient(Value::String(key)), Lenient(value))),
+                );
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.parse(Value::is_boolean).deserialize_bool(visitor)
+    }
+
+    deserialize_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(Lenient(value)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.parse(Value::is_null).deserialize_unit(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.parse(Value::is_null)
+            .deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        Lenient(self.parse(Value::is_array)).deserialize_any(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        Lenient(self.parse(Value::is_object)).deserialize_any(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, serde_json::Error> for Lenient {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(content: &str) -> Result<Value, ParseError> {
+    Ok(toml::from_str(content)?)
+}
+
+fn parse_json(content: &str) -> Result<Value, ParseError> {
+    Ok(serde_json::from_str(content)?)
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(content: &str) -> Result<Value, ParseError> {
+    Ok(serde_norway::from_str(content)?)
+}
@@ -0,0 +1,142 @@
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{Directories, DirectoryKind};
+
+/// Container directories are meant for applications that run inside a container, like with
+/// Docker, Podman or Kubernetes. Container images conventionally expose the application's
+/// directories as volumes directly under the file system root, so they can be mounted easily.
+///
+/// The folders are defined as follows, with `<root>` being either `/` or a user defined directory
+/// given to [`ContainerDirs::new_at`]:
+///
+/// | Type    | Location         |
+/// | ------- | ---------------- |
+/// | Cache   | `<root>`/cache   |
+/// | Config  | `<root>`/config  |
+/// | Data    | `<root>`/data    |
+/// | State   | _none_           |
+/// | Runtime | _none_           |
+/// | Log     | _none_           |
+///
+/// There are no state, runtime or log directories by default, as containers usually keep state in
+/// the data volume and write logs to the standard output. Each location can be changed
+/// individually with [`ContainerDirs::with_dir`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::struct_field_names)]
+pub struct ContainerDirs {
+    cache_dir: Utf8PathBuf,
+    config_dir: Utf8PathBuf,
+    data_dir: Utf8PathBuf,
+    state_dir: Option<Utf8PathBuf>,
+    runtime_dir: Option<Utf8PathBuf>,
+    log_dir: Option<Utf8PathBuf>,
+}
+
+impl ContainerDirs {
+    /// Create a default instance, with the volumes located directly under the file system root.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{ContainerDirs, Directories};
+    ///
+    /// let dirs = ContainerDirs::new();
+    ///
+    /// assert_eq!("/data", dirs.data_dir());
+    /// assert_eq!(None, dirs.log_dir());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Self::new_at("/")
+    }
+
+    /// Create an instance with the volumes located under the given root directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{ContainerDirs, Directories};
+    ///
+    /// let dirs = ContainerDirs::new_at("/srv");
+    ///
+    /// assert_eq!("/srv/config", dirs.config_dir());
+    /// ```
+    pub fn new_at(root: impl AsRef<Utf8Path>) -> Self {
+        fn inner(root: &Utf8Path) -> ContainerDirs {
+            ContainerDirs {
+                cache_dir: root.join("cache"),
+                config_dir: root.join("config"),
+                data_dir: root.join("data"),
+                state_dir: None,
+                runtime_dir: None,
+                log_dir: None,
+            }
+        }
+
+        inner(root.as_ref())
+    }
+
+    /// Change the location of a single directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{ContainerDirs, Directories, DirectoryKind};
+    ///
+    /// let dirs = ContainerDirs::new()
+    ///     .with_dir(DirectoryKind::Cache, "/tmp/cache")
+    ///     .with_dir(DirectoryKind::Log, "/logs");
+    ///
+    /// assert_eq!("/tmp/cache", dirs.cache_dir());
+    /// assert_eq!("/logs", dirs.log_dir().unwrap());
+    /// ```
+    #[must_use]
+    pub fn with_dir(mut self, kind: DirectoryKind, path: impl Into<Utf8PathBuf>) -> Self {
+        self.set_dir(kind, path.into());
+        self
+    }
+
+    /// Replace the location of a single directory.
+    pub(crate) fn set_dir(&mut self, kind: DirectoryKind, path: Utf8PathBuf) {
+        match kind {
+            DirectoryKind::Cache => self.cache_dir = path,
+            DirectoryKind::Config => self.config_dir = path,
+            DirectoryKind::Data => self.data_dir = path,
+            DirectoryKind::State => self.state_dir = Some(path),
+            DirectoryKind::Runtime => self.runtime_dir = Some(path),
+            DirectoryKind::Log => self.log_dir = Some(path),
+        }
+    }
+}
+
+impl Default for ContainerDirs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Directories for ContainerDirs {
+    fn cache_dir(&self) -> &Utf8Path {
+        &self.cache_dir
+    }
+
+    fn config_dir(&self) -> &Utf8Path {
+        &self.config_dir
+    }
+
+    fn data_dir(&self) -> &Utf8Path {
+        &self.data_dir
+    }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        self.state_dir.as_deref()
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        self.log_dir.as_deref()
+    }
+}
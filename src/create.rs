@@ -0,0 +1,134 @@
+use std::{fs, io};
+
+use camino::Utf8Path;
+
+use crate::{Directories, DirectoryKind, Error};
+
+/// Mode that is always used for the runtime directory, regardless of the backend.
+const RUNTIME_MODE: u32 = 0o700;
+
+pub(crate) fn create_all<D>(dirs: &D) -> Result<Vec<DirectoryKind>, Error>
+where
+    D: Directories + ?Sized,
+{
+    let mut seen = Vec::<(&Utf8Path, bool)>::new();
+    let mut created = Vec::new();
+
+    for kind in DirectoryKind::ALL {
+        let Some(path) = dirs.dir(kind) else {
+            continue;
+        };
+
+        let new = if let Some((_, new)) = seen.iter().find(|(seen, _)| *seen == path) {
+            *new
+        } else {
+            let runtime = kind == DirectoryKind::Runtime;
+            let mode = if runtime {
+                RUNTIME_MODE
+            } else {
+                dirs.dir_mode(kind)
+            };
+            let new = create(path, mode, runtime, !runtime && dirs.root_owned(kind))?;
+            seen.push((path, new));
+            new
+        };
+
+        if new {
+            created.push(kind);
+        }
+    }
+
+    Ok(created)
+}
+
+/// Create a single directory with the given mode, or verify the existing one. Returns whether the
+/// directory was newly created.
+fn create(path: &Utf8Path, mode: u32, private: bool, root_owned: bool) -> Result<bool, Error> {
+    let io_err = |source| Error::Io {
+        path: path.to_owned(),
+        source,
+    };
+
+    // Parents keep the default permissions, as they may be shared with directories of other kinds
+    // that need to be reachable by other users.
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_err)?;
+    }
+
+    match dir_builder(mode).create(path) {
+        Ok(()) => {
+            set_mode(path, mode).map_err(io_err)?;
+            Ok(true)
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            verify(path, private, root_owned)?;
+            Ok(false)
+        }
+        Err(e) => Err(io_err(e)),
+    }
+}
+
+/// Create a builder for a single directory, that applies the mode on creation.
+#[cfg(unix)]
+fn dir_builder(mode: u32) -> fs::DirBuilder {
+    use std::os::unix::fs::DirBuilderExt;
+
+    let mut builder = fs::DirBuilder::new();
+    builder.mode(mode);
+    builder
+}
+
+#[cfg(not(unix))]
+fn dir_builder(_mode: u32) -> fs::DirBuilder {
+    fs::DirBuilder::new()
+}
+
+/// Explicitly set the permissions, as the mode given during creation is restricted by the umask.
+#[cfg(unix)]
+fn set_mode(path: &Utf8Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+fn set_mode(_path: &Utf8Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Ensure an existing directory is safe to use.
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn verify(path: &Utf8Path, private: bool, root_owned: bool) -> Result<(), Error> {
+    let metadata = fs::metadata(path).map_err(|source| Error::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+
+    if !metadata.is_dir() {
+        return Err(Error::NotADirectory(path.to_owned()));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let mode = metadata.mode() & 0o7777;
+        if mode & 0o002 != 0 || (private && mode & 0o077 != 0) {
+            return Err(Error::InsecurePermissions {
+                path: path.to_owned(),
+                mode,
+            });
+        }
+
+        let uid = metadata.uid();
+        if !(uid == rustix::process::geteuid().as_raw() || (root_owned && uid == 0)) {
+            return Err(Error::ForeignOwner {
+                path: path.to_owned(),
+                uid,
+            });
+        }
+    }
+
+    Ok(())
+}
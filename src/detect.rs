@@ -0,0 +1,630 @@
+use std::path::Path;
+
+use crate::{unified::Mode, Environment, Platform};
+
+/// Detect whether the process was started by systemd, and whether it's run by the system or a
+/// user's service manager.
+pub(crate) fn systemd(env: &dyn Environment) -> Option<Mode> {
+    const VARS: [&str; 3] = ["INVOCATION_ID", "JOURNAL_STREAM", "NOTIFY_SOCKET"];
+
+    let vars = VARS.iter().any(|var| env.var_os(var).is_some());
+    let cgroup = env.read_to_string(Path::new("/proc/self/cgroup")).ok();
+
+    match cgroup.as_deref().and_then(systemd_unit) {
+        Some(unit) if unit.user && (unit.service || vars) => Some(Mode::User),
+        Some(unit) => unit.service.then_some(Mode::Service),
+        None if vars => {
+            // Without cgroup information, the notification socket is the only remaining hint for
+            // a user's service manager.
+            let user = env
+                .var_os("NOTIFY_SOCKET")
+                .is_some_and(|socket| socket.to_string_lossy().starts_with("/run/user/"));
+            Some(if user { Mode::User } else { Mode::Service })
+        }
+        None => None,
+    }
+}
+
+/// Information about the systemd unit a process belongs to.
+struct SystemdUnit {
+    /// The unit is managed by a user's service manager (`systemd --user`).
+    user: bool,
+    /// The process' own cgroup is a service unit.
+    service: bool,
+}
+
+/// Extract the systemd unit information from the contents of `/proc/self/cgroup`.
+fn systemd_unit(cgroup: &str) -> Option<SystemdUnit> {
+    // Prefer the unified hierarchy (cgroup v2), then fall back to the systemd controller of the
+    // legacy hierarchy.
+    let path = cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .or_else(|| {
+            cgroup
+                .lines()
+                .find_map(|line| line.split_once(":name=systemd:").map(|(_, path)| path))
+        })?;
+
+    let path = path.trim();
+    if path == "/" {
+        return None;
+    }
+
+    Some(SystemdUnit {
+        user: path.split('/').any(|part| part.starts_with("user@")),
+        service: path
+            .rsplit('/')
+            .next()
+            .is_some_and(|unit| unit.ends_with(".service")),
+    })
+}
+
+/// First and last user ID that systemd allocates for units with `DynamicUser=` enabled.
+const DYNAMIC_UID_RANGE: (u32, u32) = (61184, 65519);
+
+/// Detect whether the process is run by a system account, judged by its user ID and the account
+/// details in `/etc/passwd`.
+pub(crate) fn system_account(env: &dyn Environment) -> Option<Mode> {
+    system_account_on(env, Platform::current())
+}
+
+fn system_account_on(env: &dyn Environment, platform: Platform) -> Option<Mode> {
+    let uid = env.uid()?;
+    if uid == 0 {
+        return None;
+    }
+
+    let max = env
+        .read_to_string(Path::new("/etc/login.defs"))
+        .map_or_else(
+            |_| default_system_uid_max(platform),
+            |defs| system_uid_max(&defs),
+        );
+
+    let system = (1..=max).contains(&uid)
+        || (DYNAMIC_UID_RANGE.0..=DYNAMIC_UID_RANGE.1).contains(&uid)
+        || env
+            .read_to_string(Path::new("/etc/passwd"))
+            .is_ok_and(|passwd| no_login(env, &passwd, uid));
+
+    system.then_some(Mode::Service)
+}
+
+/// Highest system user ID on platforms without `/etc/login.defs`. macOS creates regular users from
+/// 501 upwards, while most other Unix systems start at 1000.
+const fn default_system_uid_max(platform: Platform) -> u32 {
+    match platform {
+        Platform::MacOs => 499,
+        _ => 999,
+    }
+}
+
+/// Determine the highest system user ID from the contents of `/etc/login.defs`, which is
+/// `SYS_UID_MAX` or below `UID_MIN` (defaulting to 1000) if not set.
+///
+/// The range of system accounts always starts at 1, as `SYS_UID_MIN` only limits the dynamic
+/// allocation of new accounts, while statically allocated ones (like `apache` on Fedora) are
+/// located below it.
+fn system_uid_max(defs: &str) -> u32 {
+    let value = |key: &str| {
+        defs.lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .find_map(|line| {
+                let (name, value) = line.split_once(char::is_whitespace)?;
+                if name != key {
+                    return None;
+                }
+                value.trim().parse::<u32>().ok()
+            })
+    };
+
+    value("SYS_UID_MAX").unwrap_or_else(|| value("UID_MIN").unwrap_or(1000).saturating_sub(1))
+}
+
+/// Check whether the account with the given user ID can't log in, judged by its shell or home
+/// directory in the contents of `/etc/passwd`.
+fn no_login(env: &dyn Environment, passwd: &str, uid: u32) -> bool {
+    passwd
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.get(2).and_then(|id| id.parse().ok()) == Some(uid))
+        .is_some_and(|fields| {
+            let home = fields.get(5).copied().unwrap_or_default();
+            let shell = fields.get(6).copied().unwrap_or_default();
+
+            shell.ends_with("/nologin")
+                || shell.ends_with("/false")
+                || home.is_empty()
+                || !env.is_dir(Path::new(home))
+        })
+}
+
+/// Known process supervisors, as they appear in `/proc/<pid>/comm`. The system's systemd instance
+/// is covered by PID 1, while any other instance is a user's service manager.
+const SUPERVISORS: [&str; 6] = [
+    "supervisord",
+    "runsv",
+    "s6-supervise",
+    "tini",
+    "dumb-init",
+    "docker-init",
+];
+
+/// Fields of `/proc/self/stat`, starting with the process state (the third field). Everything
+/// before is skipped, as the executable name may contain spaces and parentheses itself.
+fn stat_fields(stat: &str) -> Vec<&str> {
+    stat.rsplit_once(')')
+        .map(|(_, fields)| fields.split_whitespace().collect())
+        .unwrap_or_default()
+}
+
+/// Detect whether the process has no controlling terminal.
+pub(crate) fn no_tty(env: &dyn Environment) -> Option<Mode> {
+    let stat = env.read_to_string(Path::new("/proc/self/stat")).ok()?;
+    let tty = stat_fields(&stat).get(4)?.parse::<i32>().ok()?;
+
+    (tty == 0).then_some(Mode::Service)
+}
+
+/// Detect whether the standard input of the process is redirected from `/dev/null`.
+pub(crate) fn null_stdin(env: &dyn Environment) -> Option<Mode> {
+    let stdin = env.read_link(Path::new("/proc/self/fd/0")).ok()?;
+
+    (stdin == Path::new("/dev/null")).then_some(Mode::Service)
+}
+
+/// Detect whether the parent process is the init process or a known process supervisor.
+pub(crate) fn parent_process(env: &dyn Environment) -> Option<Mode> {
+    let stat = env.read_to_string(Path::new("/proc/self/stat")).ok()?;
+    let ppid = stat_fields(&stat).get(1)?.parse::<u32>().ok()?;
+
+    if ppid == 1 {
+        return Some(Mode::Service);
+    }
+
+    let comm = env
+        .read_to_string(Path::new(&format!("/proc/{ppid}/comm")))
+        .ok()?;
+
+    SUPERVISORS
+        .contains(&comm.trim_end())
+        .then_some(Mode::Service)
+}
+
+/// Names in the cgroup of the init process that indicate an application container runtime. LXC is
+/// left out, as it runs system containers with their own init and service manager.
+const CONTAINER_CGROUPS: [&str; 4] = ["docker", "kubepods", "containerd", "libpod"];
+
+/// Detect whether the process runs inside an application container.
+pub(crate) fn container(env: &dyn Environment) -> Option<Mode> {
+    let container = env.is_file(Path::new("/.dockerenv"))
+        || env.is_file(Path::new("/run/.containerenv"))
+        || env.var_os("KUBERNETES_SERVICE_HOST").is_some()
+        || env
+            .var_os("container")
+            .is_some_and(|name| ["docker", "oci", "podman"].iter().any(|n| name == *n))
+        || env
+            .read_to_string(Path::new("/proc/1/cgroup"))
+            .is_ok_and(|cgroup| CONTAINER_CGROUPS.iter().any(|name| cgroup.contains(name)));
+
+    container.then_some(Mode::Container)
+}
+
+/// Prefixes of system locations, where an executable is installed instead of being portable.
+#[cfg(not(windows))]
+const SYSTEM_PREFIXES: [&str; 10] = [
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/opt",
+    "/nix",
+    "/snap",
+    "/Applications",
+    "/Library",
+    "/System",
+];
+
+/// Detect whether a marker file exists next to the executable, that enables the portable mode.
+pub(crate) fn portable(env: &dyn Environment, application: &str) -> Option<Mode> {
+    let exe = env.current_exe().ok()?;
+    let dir = exe.parent()?;
+
+    let marker = env.is_file(&dir.join("portable.txt"))
+        || env.is_file(&dir.join(format!("{application}.portable")));
+
+    marker.then_some(Mode::Portable)
+}
+
+/// Detect whether the executable is located in a writable directory outside of the common system
+/// and installation locations.
+pub(crate) fn portable_writable(env: &dyn Environment) -> Option<Mode> {
+    let exe = env.current_exe().ok()?;
+    let dir = exe.parent()?;
+
+    let installed = dir
+        .file_name()
+        .is_some_and(|name| name == "bin" || name == "sbin")
+        || system_prefix(env, dir);
+
+    (!installed && !cargo_target(dir) && env.is_writable(dir)).then_some(Mode::Portable)
+}
+
+/// Check whether the directory is part of a cargo build output, like `target/release`,
+/// `target/<triple>/debug` or the `deps` and `examples` folders within.
+fn cargo_target(dir: &Path) -> bool {
+    dir.ancestors().any(|dir| {
+        dir.file_name()
+            .is_some_and(|name| name == "debug" || name == "release")
+            && dir
+                .ancestors()
+                .skip(1)
+                .take(2)
+                .any(|parent| parent.file_name().is_some_and(|name| name == "target"))
+    })
+}
+
+#[cfg(not(windows))]
+fn system_prefix(_env: &dyn Environment, dir: &Path) -> bool {
+    SYSTEM_PREFIXES.iter().any(|prefix| dir.starts_with(prefix))
+}
+
+#[cfg(windows)]
+fn system_prefix(env: &dyn Environment, dir: &Path) -> bool {
+    [
+        "ProgramFiles",
+        "ProgramFiles(x86)",
+        "ProgramW6432",
+        "SystemRoot",
+    ]
+    .iter()
+    .filter_map(|name| env.var_os(name))
+    .any(|prefix| dir.starts_with(prefix))
+}
+
+/// Match a text against a glob pattern, where `*` matches any number of characters and `?`
+/// matches a single character.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    backtrack = Some((bp, bt + 1));
+                    p = bp + 1;
+                    t = bt + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockEnv;
+
+    fn cgroup(contents: &str) -> MockEnv {
+        MockEnv::new().with_file("/proc/self/cgroup", contents)
+    }
+
+    #[test]
+    fn systemd_system_unit() {
+        let env = cgroup("0::/system.slice/app.service\n");
+        assert_eq!(Some(Mode::Service), systemd(&env));
+    }
+
+    #[test]
+    fn systemd_user_unit() {
+        let env =
+            cgroup("0::/user.slice/user-1000.slice/user@1000.service/app.slice/app.service\n");
+        assert_eq!(Some(Mode::User), systemd(&env));
+    }
+
+    #[test]
+    fn systemd_session_scope() {
+        let env = cgroup("0::/user.slice/user-1000.slice/session-2.scope\n");
+        assert_eq!(None, systemd(&env));
+    }
+
+    #[test]
+    fn systemd_root_cgroup() {
+        assert!(systemd_unit("0::/\n").is_none());
+    }
+
+    #[test]
+    fn systemd_legacy_hierarchy() {
+        let unit = systemd_unit(
+            "12:pids:/user.slice/user-1000.slice/session-2.scope\n\
+             1:name=systemd:/system.slice/app.service\n",
+        );
+        assert!(matches!(
+            unit,
+            Some(SystemdUnit {
+                user: false,
+                service: true
+            })
+        ));
+
+        let env = cgroup("1:name=systemd:/system.slice/app.service\n");
+        assert_eq!(Some(Mode::Service), systemd(&env));
+    }
+
+    #[test]
+    fn systemd_notify_socket() {
+        let env = MockEnv::new().with_var("NOTIFY_SOCKET", "/run/user/1000/systemd/notify");
+        assert_eq!(Some(Mode::User), systemd(&env));
+
+        let env = MockEnv::new().with_var("NOTIFY_SOCKET", "/run/systemd/notify");
+        assert_eq!(Some(Mode::Service), systemd(&env));
+    }
+
+    #[test]
+    fn systemd_absent() {
+        assert_eq!(None, systemd(&MockEnv::new()));
+    }
+
+    #[test]
+    fn glob_literal() {
+        assert!(glob_match("app", "app"));
+        assert!(!glob_match("app", "app2"));
+        assert!(!glob_match("app", "ap"));
+    }
+
+    #[test]
+    fn glob_star() {
+        assert!(glob_match("svc-*", "svc-app"));
+        assert!(glob_match("svc-*", "svc-"));
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(!glob_match("*a*b", "xaxxabx"));
+        assert!(!glob_match("svc-*", "web-app"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("app?", "app2"));
+        assert!(glob_match("app-?", "app-1"));
+        assert!(!glob_match("app?", "app"));
+        assert!(!glob_match("app?", "app12"));
+    }
+
+    #[test]
+    fn system_account_default_range() {
+        let env = MockEnv::new().with_uid(501);
+        assert_eq!(
+            Some(Mode::Service),
+            system_account_on(&env, Platform::Linux)
+        );
+        assert_eq!(None, system_account_on(&env, Platform::MacOs));
+
+        let env = MockEnv::new().with_uid(499);
+        assert_eq!(
+            Some(Mode::Service),
+            system_account_on(&env, Platform::MacOs)
+        );
+    }
+
+    #[test]
+    fn system_account_login_defs() {
+        let env = MockEnv::new()
+            .with_uid(501)
+            .with_file("/etc/login.defs", "UID_MIN 500\n");
+        assert_eq!(None, system_account_on(&env, Platform::Linux));
+
+        let env = MockEnv::new()
+            .with_uid(48)
+            .with_file("/etc/login.defs", "SYS_UID_MIN 201\nSYS_UID_MAX 999\n");
+        assert_eq!(
+            Some(Mode::Service),
+            system_account_on(&env, Platform::Linux)
+        );
+    }
+
+    #[test]
+    fn system_account_root() {
+        let env = MockEnv::new().with_uid(0);
+        assert_eq!(None, system_account_on(&env, Platform::Linux));
+    }
+
+    fn stat(contents: &str) -> MockEnv {
+        MockEnv::new().with_file("/proc/self/stat", contents)
+    }
+
+    #[test]
+    fn stat_fields_after_name() {
+        let fields = stat_fields("42 (app) S 1 42 42 0 -1 4194560");
+        assert_eq!(["S", "1", "42", "42", "0", "-1", "4194560"], fields[..]);
+    }
+
+    #[test]
+    fn stat_fields_name_with_parentheses() {
+        let fields = stat_fields("42 (my) (app) S 1 42 42 0 -1");
+        assert_eq!(["S", "1", "42", "42", "0", "-1"], fields[..]);
+    }
+
+    #[test]
+    fn stat_fields_invalid() {
+        assert!(stat_fields("").is_empty());
+        assert!(stat_fields("42 app S 1").is_empty());
+    }
+
+    #[test]
+    fn no_tty_detached() {
+        let env = stat("42 (app) S 1 42 42 0 -1 4194560");
+        assert_eq!(Some(Mode::Service), no_tty(&env));
+
+        let env = stat("42 (my) (app) S 1 42 42 0 -1 4194560");
+        assert_eq!(Some(Mode::Service), no_tty(&env));
+    }
+
+    #[test]
+    fn no_tty_terminal() {
+        let env = stat("42 (app) S 7 42 7 34816 42 4194304");
+        assert_eq!(None, no_tty(&env));
+        assert_eq!(None, no_tty(&MockEnv::new()));
+    }
+
+    #[test]
+    fn null_stdin_redirected() {
+        let env = MockEnv::new().with_link("/proc/self/fd/0", "/dev/null");
+        assert_eq!(Some(Mode::Service), null_stdin(&env));
+    }
+
+    #[test]
+    fn null_stdin_terminal() {
+        let env = MockEnv::new().with_link("/proc/self/fd/0", "/dev/pts/0");
+        assert_eq!(None, null_stdin(&env));
+        assert_eq!(None, null_stdin(&MockEnv::new()));
+    }
+
+    #[test]
+    fn parent_process_init() {
+        let env = stat("42 (app) S 1 42 42 0");
+        assert_eq!(Some(Mode::Service), parent_process(&env));
+    }
+
+    #[test]
+    fn parent_process_supervisor() {
+        for name in SUPERVISORS {
+            let env = stat("42 (app) S 7 42 42 0").with_file("/proc/7/comm", format!("{name}\n"));
+            assert_eq!(Some(Mode::Service), parent_process(&env), "{name}");
+        }
+    }
+
+    #[test]
+    fn parent_process_shell() {
+        let env = stat("42 (app) S 7 42 42 0").with_file("/proc/7/comm", "bash\n");
+        assert_eq!(None, parent_process(&env));
+
+        let env = stat("42 (app) S 7 42 42 0");
+        assert_eq!(None, parent_process(&env));
+    }
+
+    #[test]
+    fn container_marker_files() {
+        let env = MockEnv::new().with_file("/.dockerenv", "");
+        assert_eq!(Some(Mode::Container), container(&env));
+
+        let env = MockEnv::new().with_file("/run/.containerenv", "");
+        assert_eq!(Some(Mode::Container), container(&env));
+    }
+
+    #[test]
+    fn container_vars() {
+        let env = MockEnv::new().with_var("KUBERNETES_SERVICE_HOST", "10.96.0.1");
+        assert_eq!(Some(Mode::Container), container(&env));
+
+        for name in ["docker", "oci", "podman"] {
+            let env = MockEnv::new().with_var("container", name);
+            assert_eq!(Some(Mode::Container), container(&env), "{name}");
+        }
+    }
+
+    #[test]
+    fn container_init_cgroup() {
+        let env =
+            MockEnv::new().with_file("/proc/1/cgroup", "0::/kubepods/besteffort/pod3f6a/0b8e\n");
+        assert_eq!(Some(Mode::Container), container(&env));
+
+        let env = MockEnv::new().with_file("/proc/1/cgroup", "0::/init.scope\n");
+        assert_eq!(None, container(&env));
+    }
+
+    #[test]
+    fn container_system_container() {
+        let env = MockEnv::new().with_var("container", "lxc-libvirt");
+        assert_eq!(None, container(&env));
+
+        let env = MockEnv::new().with_file("/proc/1/cgroup", "0::/lxc.payload.c1\n");
+        assert_eq!(None, container(&env));
+        assert_eq!(None, container(&MockEnv::new()));
+    }
+
+    fn exe(path: &str) -> MockEnv {
+        MockEnv::new().with_current_exe(path)
+    }
+
+    fn writable_exe(path: &str) -> MockEnv {
+        let dir = Path::new(path).parent().unwrap_or(Path::new("/"));
+        exe(path).with_writable_dir(dir)
+    }
+
+    #[test]
+    fn portable_marker() {
+        let env = exe("/opt/app/app").with_file("/opt/app/portable.txt", "");
+        assert_eq!(Some(Mode::Portable), portable(&env, "app"));
+
+        let env = exe("/opt/app/app").with_file("/opt/app/app.portable", "");
+        assert_eq!(Some(Mode::Portable), portable(&env, "app"));
+    }
+
+    #[test]
+    fn portable_other_marker() {
+        let env = exe("/opt/app/app").with_file("/opt/app/other.portable", "");
+        assert_eq!(None, portable(&env, "app"));
+        assert_eq!(None, portable(&exe("/opt/app/app"), "app"));
+    }
+
+    #[test]
+    fn portable_writable_dir() {
+        let env = writable_exe("/home/alice/apps/app/app");
+        assert_eq!(Some(Mode::Portable), portable_writable(&env));
+
+        let env = exe("/home/alice/apps/app/app");
+        assert_eq!(None, portable_writable(&env));
+    }
+
+    #[test]
+    fn portable_writable_bin() {
+        let env = writable_exe("/home/alice/.local/bin/app");
+        assert_eq!(None, portable_writable(&env));
+
+        let env = writable_exe("/home/alice/sbin/app");
+        assert_eq!(None, portable_writable(&env));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn portable_writable_system_prefix() {
+        let env = writable_exe("/usr/local/app/app");
+        assert_eq!(None, portable_writable(&env));
+
+        let env = writable_exe("/opt/app/app");
+        assert_eq!(None, portable_writable(&env));
+    }
+
+    #[test]
+    fn portable_writable_cargo_target() {
+        let env = writable_exe("/home/alice/project/target/release/app");
+        assert_eq!(None, portable_writable(&env));
+    }
+
+    #[test]
+    fn cargo_target_dirs() {
+        assert!(cargo_target(Path::new("/project/target/debug")));
+        assert!(cargo_target(Path::new("/project/target/release/examples")));
+        assert!(cargo_target(Path::new(
+            "/project/target/x86_64-unknown-linux-gnu/debug/deps"
+        )));
+        assert!(!cargo_target(Path::new("/project/release")));
+        assert!(!cargo_target(Path::new("/project/target/a/b/debug")));
+    }
+}
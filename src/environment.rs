@@ -0,0 +1,411 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::{OsStr, OsString},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Access to the process environment that the directories are resolved from, including the few
+/// system files that the detection techniques of the [`SimpleBuilder`](crate::SimpleBuilder)
+/// inspect, like `/etc/passwd` or `/proc/self/stat`. All constructors
+/// that read from the environment have a `*_in` variant, that accepts an implementation of this
+/// trait, and the [`SimpleBuilder`](crate::SimpleBuilder) can be configured with one through
+/// [`environment`](crate::SimpleBuilder::environment).
+///
+/// [`HostEnv`] is the default implementation that accesses the real environment, while [`MockEnv`]
+/// allows to define all values up front, for example to run tests in parallel without modifying
+/// the global environment variables.
+pub trait Environment {
+    /// The value of an environment variable, see [`std::env::var_os`].
+    fn var_os(&self, name: &str) -> Option<OsString>;
+
+    /// All environment variables, see [`std::env::vars_os`].
+    fn vars_os(&self) -> Vec<(OsString, OsString)>;
+
+    /// The command line arguments, including the program name, see [`std::env::args_os`].
+    fn args_os(&self) -> Vec<OsString>;
+
+    /// The current working directory, see [`std::env::current_dir`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the directory can't be determined.
+    fn current_dir(&self) -> io::Result<PathBuf>;
+
+    /// The home directory of the current user.
+    fn home_dir(&self) -> Option<PathBuf>;
+
+    /// The account name of the current user.
+    fn username_os(&self) -> OsString;
+
+    /// The user ID of the current user, or [`None`] on platforms without user IDs.
+    fn uid(&self) -> Option<u32>;
+
+    /// The path of the current executable, see [`std::env::current_exe`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path can't be determined.
+    fn current_exe(&self) -> io::Result<PathBuf>;
+
+    /// The contents of a file, see [`std::fs::read_to_string`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file doesn't exist or can't be read.
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// The target of a symbolic link, see [`std::fs::read_link`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path doesn't exist or is not a symbolic link.
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf>;
+
+    /// Whether the path exists and is a file, see [`Path::is_file`].
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Whether the path exists and is a directory, see [`Path::is_dir`].
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Whether the current user can create files in the directory.
+    fn is_writable(&self, dir: &Path) -> bool;
+
+    /// The location of a known folder of the current user on Windows. By default, it's read from
+    /// the matching environment variable (like `APPDATA`), while [`HostEnv`] asks the system on
+    /// Windows.
+    fn known_folder(&self, folder: KnownFolder) -> Option<PathBuf> {
+        self.var_os(folder.var()).map(PathBuf::from)
+    }
+}
+
+/// Known folders of a Windows user account, that the [`UserDirs`](crate::UserDirs) are located in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum KnownFolder {
+    /// The roaming application data, usually `C:\Users\<user>\AppData\Roaming`.
+    RoamingAppData,
+    /// The local application data, usually `C:\Users\<user>\AppData\Local`.
+    LocalAppData,
+}
+
+impl KnownFolder {
+    /// The environment variable that holds the folder's location.
+    #[must_use]
+    pub const fn var(self) -> &'static str {
+        match self {
+            Self::RoamingAppData => "APPDATA",
+            Self::LocalAppData => "LOCALAPPDATA",
+        }
+    }
+}
+
+/// The environment of the current process. Passing it to any of the `*_in` constructors gives
+/// the same results as their counterparts without the suffix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HostEnv;
+
+impl Environment for HostEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        env::var_os(name)
+    }
+
+    fn vars_os(&self) -> Vec<(OsString, OsString)> {
+        env::vars_os().collect()
+    }
+
+    fn args_os(&self) -> Vec<OsString> {
+        env::args_os().collect()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        env::current_dir()
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        directories::BaseDirs::new().map(|base| base.home_dir().to_owned())
+    }
+
+    fn username_os(&self) -> OsString {
+        whoami::username_os()
+    }
+
+    #[cfg(unix)]
+    fn uid(&self) -> Option<u32> {
+        Some(rustix::process::getuid().as_raw())
+    }
+
+    #[cfg(not(unix))]
+    fn uid(&self) -> Option<u32> {
+        None
+    }
+
+    fn current_exe(&self) -> io::Result<PathBuf> {
+        env::current_exe()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        fs::read_link(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    #[cfg(unix)]
+    fn is_writable(&self, dir: &Path) -> bool {
+        rustix::fs::access(dir, rustix::fs::Access::WRITE_OK).is_ok()
+    }
+
+    /// The read-only attribute of directories doesn't reflect whether files can be created in them
+    /// on Windows. Therefore, this is tested by creating a temporary file.
+    #[cfg(not(unix))]
+    fn is_writable(&self, dir: &Path) -> bool {
+        let path = dir.join(format!(".unidirs-{}.tmp", std::process::id()));
+        let created = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .is_ok();
+
+        if created {
+            let _ = fs::remove_file(&path);
+        }
+
+        created
+    }
+
+    #[cfg(windows)]
+    fn known_folder(&self, folder: KnownFolder) -> Option<PathBuf> {
+        let base = directories::BaseDirs::new()?;
+
+        Some(match folder {
+            KnownFolder::RoamingAppData => base.data_dir().to_owned(),
+            KnownFolder::LocalAppData => base.data_local_dir().to_owned(),
+        })
+    }
+}
+
+/// An environment with predefined values, that doesn't access the process environment at all.
+///
+/// Anything that isn't set explicitly is considered missing. Accordingly, the current directory
+/// and executable fail with an [`io::ErrorKind::NotFound`] error, and the file system only
+/// contains the files, links and directories that were added to it.
+///
+/// # Examples
+///
+/// ```rust
+/// use unidirs::{Directories, LocalDirs, MockEnv};
+///
+/// let env = MockEnv::new().with_current_dir("/project");
+/// let dirs = LocalDirs::new_in(&env).unwrap();
+///
+/// assert_eq!("/project/.local/data", dirs.data_dir());
+/// ```
+///
+/// The file system allows to test the detection techniques, that inspect system files:
+///
+/// ```rust
+/// use unidirs::{MockEnv, Mode, UnifiedDirs};
+///
+/// let env = MockEnv::new()
+///     .with_current_dir("/app")
+///     .with_uid(1001)
+///     .with_file("/etc/passwd", "app:x:1001:1001::/var/lib/app:/usr/sbin/nologin\n");
+/// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+///     .environment(env)
+///     .with_system_account()
+///     .build_with_trace()
+///     .unwrap();
+///
+/// assert_eq!(Some(Mode::Service), trace.steps()[0].outcome().mode());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockEnv {
+    vars: BTreeMap<String, OsString>,
+    args: Vec<OsString>,
+    current_dir: Option<PathBuf>,
+    home_dir: Option<PathBuf>,
+    username: OsString,
+    uid: Option<u32>,
+    current_exe: Option<PathBuf>,
+    fs: BTreeMap<PathBuf, Entry>,
+}
+
+/// An entry in the file system of a [`MockEnv`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    File(String),
+    Link(PathBuf),
+    Dir { writable: bool },
+}
+
+impl MockEnv {
+    /// Create an empty environment.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an environment variable.
+    #[must_use]
+    pub fn with_var(mut self, name: impl Into<String>, value: impl AsRef<OsStr>) -> Self {
+        self.vars.insert(name.into(), value.as_ref().to_owned());
+        self
+    }
+
+    /// Set the command line arguments, including the program name.
+    #[must_use]
+    pub fn with_args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the current working directory.
+    #[must_use]
+    pub fn with_current_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(path.into());
+        self
+    }
+
+    /// Set the home directory of the current user.
+    #[must_use]
+    pub fn with_home_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.home_dir = Some(path.into());
+        self
+    }
+
+    /// Set the account name of the current user.
+    #[must_use]
+    pub fn with_username(mut self, name: impl Into<OsString>) -> Self {
+        self.username = name.into();
+        self
+    }
+
+    /// Set the user ID of the current user.
+    #[must_use]
+    pub fn with_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Set the path of the current executable.
+    #[must_use]
+    pub fn with_current_exe(mut self, path: impl Into<PathBuf>) -> Self {
+        self.current_exe = Some(path.into());
+        self
+    }
+
+    /// Add a file with the given contents to the file system.
+    #[must_use]
+    pub fn with_file(mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        self.fs.insert(path.into(), Entry::File(contents.into()));
+        self
+    }
+
+    /// Add a symbolic link with the given target to the file system. The link is not followed when
+    /// checking for files or directories.
+    #[must_use]
+    pub fn with_link(mut self, path: impl Into<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        self.fs.insert(path.into(), Entry::Link(target.into()));
+        self
+    }
+
+    /// Add a directory to the file system, that the current user can't write to.
+    #[must_use]
+    pub fn with_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fs.insert(path.into(), Entry::Dir { writable: false });
+        self
+    }
+
+    /// Add a directory to the file system, that the current user can write to.
+    #[must_use]
+    pub fn with_writable_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fs.insert(path.into(), Entry::Dir { writable: true });
+        self
+    }
+}
+
+impl Environment for MockEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.vars.get(name).cloned()
+    }
+
+    fn vars_os(&self) -> Vec<(OsString, OsString)> {
+        self.vars
+            .iter()
+            .map(|(name, value)| (name.into(), value.clone()))
+            .collect()
+    }
+
+    fn args_os(&self) -> Vec<OsString> {
+        self.args.clone()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        self.current_dir
+            .clone()
+            .ok_or_else(|| not_found("no current directory set"))
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.home_dir.clone()
+    }
+
+    fn username_os(&self) -> OsString {
+        self.username.clone()
+    }
+
+    fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+
+    fn current_exe(&self) -> io::Result<PathBuf> {
+        self.current_exe
+            .clone()
+            .ok_or_else(|| not_found("no current executable set"))
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        match self.fs.get(path) {
+            Some(Entry::File(contents)) => Ok(contents.clone()),
+            _ => Err(not_found("no such file")),
+        }
+    }
+
+    fn read_link(&self, path: &Path) -> io::Result<PathBuf> {
+        match self.fs.get(path) {
+            Some(Entry::Link(target)) => Ok(target.clone()),
+            _ => Err(not_found("no such link")),
+        }
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(self.fs.get(path), Some(Entry::File(_)))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.fs.get(path), Some(Entry::Dir { .. }))
+    }
+
+    fn is_writable(&self, dir: &Path) -> bool {
+        matches!(self.fs.get(dir), Some(Entry::Dir { writable: true }))
+    }
+}
+
+fn not_found(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
@@ -0,0 +1,150 @@
+use std::{
+    fmt::{self, Display},
+    io,
+    path::{Path, PathBuf},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Platform;
+
+/// Errors that can occur while determining the directories of an application.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The current working directory couldn't be determined, for example because it was deleted
+    /// or the process lacks the permissions to read it.
+    CurrentDir(io::Error),
+    /// The path of the current executable couldn't be determined.
+    CurrentExe(io::Error),
+    /// The home directory of the current user couldn't be determined, usually because the `$HOME`
+    /// environment variable (or the platform equivalent) is missing.
+    HomeDirNotFound,
+    /// A required environment variable is not set or empty.
+    MissingEnvVar(&'static str),
+    /// A path was found, but it is not valid UTF-8.
+    NonUtf8Path(PathBuf),
+    /// A path given through an environment variable is relative, but must start at the root of
+    /// the file system.
+    RelativePath {
+        /// The name of the environment variable.
+        var: String,
+        /// The relative path.
+        path: Utf8PathBuf,
+    },
+    /// A value couldn't be parsed as [`Mode`](crate::Mode).
+    InvalidMode(String),
+    /// An I/O operation on a directory failed.
+    Io {
+        /// The directory that was operated on.
+        path: Utf8PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A path exists, but it is not a directory.
+    NotADirectory(Utf8PathBuf),
+    /// An existing directory has permissions that are too permissive.
+    InsecurePermissions {
+        /// The affected directory.
+        path: Utf8PathBuf,
+        /// The permission mode of the directory.
+        mode: u32,
+    },
+    /// An existing directory is owned by a different user.
+    ForeignOwner {
+        /// The affected directory.
+        path: Utf8PathBuf,
+        /// The user ID of the directory's owner.
+        uid: u32,
+    },
+    /// The requested directories don't exist on the platform, for example user directories on
+    /// WebAssembly targets.
+    UnsupportedPlatform(Platform),
+    /// A config file couldn't be read.
+    #[cfg(feature = "serde")]
+    ReadConfig {
+        /// The config file that was read.
+        path: Utf8PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A config file couldn't be parsed, or its content is not a table of values.
+    #[cfg(feature = "serde")]
+    ParseConfig {
+        /// The config file that was parsed.
+        path: Utf8PathBuf,
+        /// The underlying error of the file format.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The merged configuration doesn't match the structure of the requested type.
+    #[cfg(feature = "serde")]
+    InvalidConfig(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CurrentDir(_) => f.write_str("failed to determine the current working directory"),
+            Self::CurrentExe(_) => f.write_str("failed to determine the current executable"),
+            Self::HomeDirNotFound => f.write_str("failed to determine the user's home directory"),
+            Self::MissingEnvVar(name) => write!(f, "the environment variable `{name}` is not set"),
+            Self::NonUtf8Path(path) => {
+                write!(f, "the path `{}` is not valid UTF-8", path.display())
+            }
+            Self::RelativePath { var, path } => write!(
+                f,
+                "the path `{path}` in the environment variable `{var}` is not absolute"
+            ),
+            Self::InvalidMode(value) => write!(
+                f,
+                "invalid mode `{value}`, expected one of `local`, `user`, `service`, `container` or \
+                 `portable`"
+            ),
+            Self::Io { path, .. } => write!(f, "failed to access the directory `{path}`"),
+            Self::NotADirectory(path) => write!(f, "the path `{path}` is not a directory"),
+            Self::InsecurePermissions { path, mode } => write!(
+                f,
+                "the directory `{path}` has insecure permissions ({mode:#o})"
+            ),
+            Self::ForeignOwner { path, uid } => write!(
+                f,
+                "the directory `{path}` is owned by a different user ({uid})"
+            ),
+            Self::UnsupportedPlatform(platform) => {
+                write!(f, "the directories are not supported on the `{platform}` platform")
+            }
+            #[cfg(feature = "serde")]
+            Self::ReadConfig { path, .. } => write!(f, "failed to read the config file `{path}`"),
+            #[cfg(feature = "serde")]
+            Self::ParseConfig { path, .. } => write!(f, "failed to parse the config file `{path}`"),
+            #[cfg(feature = "serde")]
+            Self::InvalidConfig(_) => f.write_str("the configuration is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CurrentDir(e) | Self::CurrentExe(e) | Self::Io { source: e, .. } => Some(e),
+            Self::HomeDirNotFound
+            | Self::MissingEnvVar(_)
+            | Self::NonUtf8Path(_)
+            | Self::RelativePath { .. }
+            | Self::InvalidMode(_)
+            | Self::NotADirectory(_)
+            | Self::InsecurePermissions { .. }
+            | Self::ForeignOwner { .. }
+            | Self::UnsupportedPlatform(_) => None,
+            #[cfg(feature = "serde")]
+            Self::ReadConfig { source, .. } => Some(source),
+            #[cfg(feature = "serde")]
+            Self::ParseConfig { source, .. } | Self::InvalidConfig(source) => Some(source.as_ref()),
+        }
+    }
+}
+
+/// Convert a standard path into a UTF-8 path, keeping the original path as part of the error.
+pub(crate) fn utf8(path: &Path) -> Result<&Utf8Path, Error> {
+    Utf8Path::from_path(path).ok_or_else(|| Error::NonUtf8Path(path.to_owned()))
+}
@@ -0,0 +1,308 @@
+use std::{borrow::Cow, ffi::OsString};
+
+use crate::{unified::Mode, Environment};
+
+/// Configuration for the [`SimpleBuilder::with_env_config`](crate::SimpleBuilder::with_env_config)
+/// technique, which detects the service mode from environment variables.
+///
+/// In contrast to [`SimpleBuilder::with_env`](crate::SimpleBuilder::with_env), the variables are
+/// not only checked for presence, but their values are parsed as boolean:
+///
+/// - `1`, `true`, `yes` and `on` indicate the service mode.
+/// - `0`, `false`, `no` and `off` indicate the user mode, but are only considered if enabled with
+///   [`force_user`](Self::force_user). Otherwise, no mode is detected and the decision is left to
+///   further techniques.
+///
+/// Values are compared without regard to case and surrounding whitespace. Any other value, as well
+/// as an unset variable, is ignored and the next variable checked. The first variable with a valid
+/// value decides, even if it's false.
+///
+/// If no names are configured, the variable `<APP>_SERVICE` is used, where `<APP>` is the
+/// application name in uppercase, with any characters other than ASCII letters and digits replaced
+/// by an underscore.
+///
+/// # Example
+///
+/// ```rust
+/// use unidirs::{EnvConfig, UnifiedDirs};
+///
+/// let dirs = UnifiedDirs::simple("com", "example", "app")
+///     .with_env_config(EnvConfig::new().name("APP_DAEMON").force_user(true))
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// Without [`force_user`](Self::force_user), a false value leaves the mode undecided, even if a
+/// later variable is true:
+///
+/// ```rust
+/// use unidirs::{EnvConfig, MockEnv, UnifiedDirs};
+///
+/// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+///     .environment(
+///         MockEnv::new()
+///             .with_var("APP_SERVICE", "0")
+///             .with_var("APP_DAEMON", "1")
+///             .with_current_dir("/app"),
+///     )
+///     .with_env_config(EnvConfig::new().name("APP_SERVICE").name("APP_DAEMON"))
+///     .build_with_trace()
+///     .unwrap();
+///
+/// assert_eq!(None, trace.steps()[0].outcome().mode());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvConfig {
+    names: Vec<String>,
+    force_user: bool,
+}
+
+impl EnvConfig {
+    /// Create a new configuration, using the default variable name and not forcing the user mode.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a variable name to check, in addition to previously added ones. This replaces the default
+    /// `<APP>_SERVICE` name.
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    /// Whether a variable that is explicitly set to a false value (like `0` or `no`) selects the
+    /// user mode, instead of leaving the decision to further techniques.
+    #[must_use]
+    pub fn force_user(mut self, value: bool) -> Self {
+        self.force_user = value;
+        self
+    }
+
+    /// Evaluate the configuration, returning `Some(true)` for service mode, `Some(false)` for user
+    /// mode and `None` if undecided.
+    pub(crate) fn detect(self, env: &dyn Environment, application: &str) -> Option<bool> {
+        let default;
+        let names = if self.names.is_empty() {
+            default = [format!("{}_SERVICE", env_prefix(application))];
+            &default[..]
+        } else {
+            &self.names[..]
+        };
+
+        names
+            .iter()
+            .find_map(|name| parse_bool(env.var_os(name)?.to_str()?))
+            .filter(|&value| value || self.force_user)
+    }
+}
+
+/// Configuration for the [`SimpleBuilder::with_args_config`](crate::SimpleBuilder::with_args_config)
+/// technique, which detects the mode from command line arguments.
+///
+/// The arguments are interpreted similar to common argument parsers:
+///
+/// - Parsing stops at the first `--` argument, so anything after it is never interpreted.
+/// - Long flags are matched as `--flag` and `--flag=value`, where the value is parsed as boolean
+///   (for example `--service=false`). A false value cancels any earlier match, leaving the
+///   decision to further techniques, unless a later argument matches again.
+/// - Short flags are matched on their own (`-d`) and in groups (`-vd`).
+/// - Mode options take the [`Mode`] as value, either as `--mode=service` or `--mode service`.
+/// - Options declared to take a value consume the following argument, so that values like in
+///   `--name --service` are not mistaken for a flag.
+///
+/// If multiple arguments match, the last one wins. If no flags or mode options are configured, the
+/// flags `--service` and `--daemon` are used.
+///
+/// By default, the arguments of the current process are used (skipping the program name). An
+/// explicit list can be provided through [`args`](Self::args) instead.
+///
+/// # Example
+///
+/// ```rust
+/// use unidirs::{ArgsConfig, Mode, UnifiedDirs};
+///
+/// let config = ArgsConfig::new()
+///     .long("daemon")
+///     .short('d')
+///     .mode_option("mode")
+///     .value_option("name")
+///     .args(["--name", "--daemon", "-vd", "--mode", "user", "--", "--daemon"]);
+///
+/// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+///     .with_args_config(config)
+///     .build_with_trace()
+///     .unwrap();
+///
+/// assert_eq!(Some(Mode::User), trace.steps()[0].outcome().mode());
+/// ```
+///
+/// An explicit false value overrides an earlier flag:
+///
+/// ```rust
+/// use unidirs::{ArgsConfig, UnifiedDirs};
+///
+/// let config = ArgsConfig::new().args(["--service", "--service=false"]);
+///
+/// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+///     .with_args_config(config)
+///     .build_with_trace()
+///     .unwrap();
+///
+/// assert_eq!(None, trace.steps()[0].outcome().mode());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ArgsConfig {
+    longs: Vec<String>,
+    shorts: Vec<char>,
+    mode_options: Vec<String>,
+    value_options: Vec<String>,
+    short_value_options: Vec<char>,
+    args: Option<Vec<OsString>>,
+}
+
+impl ArgsConfig {
+    /// Create a new configuration, using the default `--service` and `--daemon` flags.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a long flag (without the leading dashes) that indicates the service mode. This replaces
+    /// the default flags.
+    #[must_use]
+    pub fn long(mut self, name: impl Into<String>) -> Self {
+        self.longs.push(name.into());
+        self
+    }
+
+    /// Add a short flag (without the leading dash) that indicates the service mode. This replaces
+    /// the default flags.
+    #[must_use]
+    pub fn short(mut self, name: char) -> Self {
+        self.shorts.push(name);
+        self
+    }
+
+    /// Add a long option (without the leading dashes) that takes the mode as value, like
+    /// `--mode=service`. This replaces the default flags.
+    #[must_use]
+    pub fn mode_option(mut self, name: impl Into<String>) -> Self {
+        self.mode_options.push(name.into());
+        self
+    }
+
+    /// Declare a long option (without the leading dashes) of the application that takes a value,
+    /// so the value is skipped when looking for flags.
+    #[must_use]
+    pub fn value_option(mut self, name: impl Into<String>) -> Self {
+        self.value_options.push(name.into());
+        self
+    }
+
+    /// Declare a short option (without the leading dash) of the application that takes a value,
+    /// so the value is skipped when looking for flags.
+    #[must_use]
+    pub fn short_value_option(mut self, name: char) -> Self {
+        self.short_value_options.push(name);
+        self
+    }
+
+    /// Use the given arguments instead of the ones passed to the current process. The list must
+    /// not contain the program name.
+    #[must_use]
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        self.args = Some(args.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Evaluate the configuration against the arguments.
+    pub(crate) fn detect(mut self, env: &dyn Environment) -> Option<Mode> {
+        if self.longs.is_empty() && self.shorts.is_empty() && self.mode_options.is_empty() {
+            self.longs = vec!["service".to_owned(), "daemon".to_owned()];
+        }
+
+        let args = self
+            .args
+            .take()
+            .unwrap_or_else(|| env.args_os().into_iter().skip(1).collect());
+        let mut args = args.iter().map(|arg| arg.to_string_lossy());
+        let mut mode = None;
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            } else if let Some(long) = arg.strip_prefix("--") {
+                let (name, value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (long, None),
+                };
+
+                if self.longs.iter().any(|l| l == name) {
+                    match value.map_or(Some(true), parse_bool) {
+                        Some(true) => mode = Some(Mode::Service),
+                        Some(false) => mode = None,
+                        None => {}
+                    }
+                } else if self.mode_options.iter().any(|o| o == name) {
+                    let value = value
+                        .map(ToOwned::to_owned)
+                        .or_else(|| args.next().map(Cow::into_owned));
+                    if let Some(value) = value.and_then(|v| v.parse().ok()) {
+                        mode = Some(value);
+                    }
+                } else if value.is_none() && self.value_options.iter().any(|o| o == name) {
+                    args.next();
+                }
+            } else if let Some(shorts) = arg.strip_prefix('-') {
+                for (i, c) in shorts.char_indices() {
+                    if self.shorts.contains(&c) {
+                        mode = Some(Mode::Service);
+                    } else if self.short_value_options.contains(&c) {
+                        if i + c.len_utf8() == shorts.len() {
+                            args.next();
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        mode
+    }
+}
+
+/// Create the prefix for application specific environment variables, by converting the name to
+/// uppercase and replacing any characters other than ASCII letters and digits with an underscore.
+pub(crate) fn env_prefix(application: &str) -> String {
+    application
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Parse common textual representations of a boolean.
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    const TRUE: [&str; 4] = ["1", "true", "yes", "on"];
+    const FALSE: [&str; 4] = ["0", "false", "no", "off"];
+
+    let value = value.trim();
+    if TRUE.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+        Some(true)
+    } else if FALSE.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+        Some(false)
+    } else {
+        None
+    }
+}
@@ -1,17 +1,31 @@
 //! Unified directories for different use cases of an application, providing standard directories
 //! for local development, when run as service or when run by a user.
 //!
-//! This crate provides 4 main structures of interest:
+//! This crate provides 6 main structures of interest:
 //!
 //! - [`LocalDirs`](crate::LocalDirs) to use a local directory as basis.
 //! - [`ServiceDirs`](crate::ServiceDirs) for use when running as a service.
 //! - [`UserDirs`](crate::UserDirs) for use when run by a local user directly.
-//! - [`UnifiedDirs`](crate::UnifiedDirs) as a combination of the above three to provide a common
+//! - [`ContainerDirs`](crate::ContainerDirs) for use when running inside a container.
+//! - [`PortableDirs`](crate::PortableDirs) to keep all files next to the executable.
+//! - [`UnifiedDirs`](crate::UnifiedDirs) as a combination of the above to provide a common
 //!   interface.
 //!
+//! All of them use UTF-8 paths. For users whose home directory is not valid UTF-8,
+//! [`OsUserDirs`](crate::OsUserDirs) together with the [`OsDirectories`](crate::OsDirectories)
+//! trait provide a fallback based on standard paths.
+//!
+//! With the `serde` feature, the `ConfigLoader` loads a typed configuration that is merged from
+//! all config directories and environment variables. JSON files are always supported, while other
+//! file formats are enabled with the `toml` and `yaml` features.
+//!
+//! For tests, the `testing` module (behind the `testing` feature) provides isolated temporary
+//! directories.
+//!
 //! The simplest, but most opinionated, way of using this crate is the [`UnifiedDirs::simple`]
-//! function. It will use the local dirs unconditionally in debug mode and uses several heuristics
-//! to decide to use service or user dirs.
+//! function. It will use the local dirs in debug mode and uses several heuristics to decide to use
+//! service or user dirs. Either decision can be overridden at runtime with the `UNIDIRS_MODE`
+//! environment variable, or in code with [`SimpleBuilder::force_mode`].
 //!
 //! Passing the boolean flag can be done in any way possible. It is very common to pass it from
 //! command line arguments or use a environment variable to detect the service mode.
@@ -34,6 +48,10 @@
 //! println!("cache dir: {}", dirs.cache_dir());
 //! println!("config dir: {}", dirs.config_dir());
 //! println!("data dir: {}", dirs.data_dir());
+//!
+//! if let Some(log_dir) = dirs.log_dir() {
+//!     println!("log dir: {log_dir}");
+//! }
 //!```
 //!
 //! ## Using `clap` to pass a flag
@@ -96,16 +114,50 @@
 )]
 #![allow(clippy::module_name_repetitions, clippy::multiple_crate_versions)]
 
+use std::{
+    fmt::{self, Display},
+    iter,
+    path::Path,
+};
+
 pub use camino::{self, Utf8Path, Utf8PathBuf};
 
+#[cfg(feature = "serde")]
+pub use crate::config::{ConfigLoader, ConfigSource, LoadedConfig};
 pub use crate::{
-    local::LocalDirs, service::ServiceDirs, simple::SimpleBuilder, unified::UnifiedDirs,
-    user::UserDirs,
+    container::ContainerDirs,
+    environment::{Environment, HostEnv, KnownFolder, MockEnv},
+    error::Error,
+    heuristic::{ArgsConfig, EnvConfig},
+    local::LocalDirs,
+    overrides::{Override, OverrideDirs},
+    platform::Platform,
+    portable::PortableDirs,
+    service::{ServiceAccount, ServiceDirs, ServiceDirsBuilder},
+    simple::SimpleBuilder,
+    trace::{ForcedBy, Outcome, Step, Trace},
+    unified::{Mode, UnifiedDirs},
+    user::{OsUserDirs, UserDirs},
 };
 
+#[cfg(feature = "serde")]
+mod config;
+mod container;
+mod create;
+mod detect;
+mod environment;
+mod error;
+mod heuristic;
 mod local;
+mod overrides;
+mod platform;
+mod portable;
+mod search;
 mod service;
 mod simple;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod trace;
 mod unified;
 mod user;
 
@@ -126,4 +178,401 @@ pub trait Directories {
     /// The data directory hold an application's state data, like a database. The folder is
     /// expected to persist during the normal runtime of the OS.
     fn data_dir(&self) -> &Utf8Path;
+
+    /// The state directory holds data that should persist between restarts, but isn't important
+    /// or portable enough to be kept in the data directory, like history files or the current
+    /// state of the application.
+    ///
+    /// Returns [`None`] if the backend has no such directory on the current platform.
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        None
+    }
+
+    /// The runtime directory holds non-essential runtime files like sockets, named pipes or PID
+    /// files. Its contents are usually removed after a reboot or logout.
+    ///
+    /// Returns [`None`] if the backend has no such directory on the current platform.
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        None
+    }
+
+    /// The log directory is where an application writes its log files.
+    ///
+    /// Returns [`None`] if the backend has no such directory on the current platform.
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        None
+    }
+
+    /// Get the directory of the given kind, dispatching to the matching `*_dir` function.
+    fn dir(&self, kind: DirectoryKind) -> Option<&Utf8Path> {
+        match kind {
+            DirectoryKind::Cache => Some(self.cache_dir()),
+            DirectoryKind::Config => Some(self.config_dir()),
+            DirectoryKind::Data => Some(self.data_dir()),
+            DirectoryKind::State => self.state_dir(),
+            DirectoryKind::Runtime => self.runtime_dir(),
+            DirectoryKind::Log => self.log_dir(),
+        }
+    }
+
+    /// Additional config directories with a lower priority than the
+    /// [`config_dir`](Self::config_dir), ordered from highest to lowest priority. These usually
+    /// hold system-wide or vendor defaults, that are overridden by the user's own configuration.
+    ///
+    /// Returns an empty list if the backend has no such directories.
+    fn system_config_dirs(&self) -> &[Utf8PathBuf] {
+        &[]
+    }
+
+    /// All config directories, starting with the [`config_dir`](Self::config_dir) and followed by
+    /// the [`system_config_dirs`](Self::system_config_dirs), from highest to lowest priority.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::new("example", "app");
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!(vec!["/etc/app", "/usr/lib/app"], dirs.config_dirs());
+    /// ```
+    fn config_dirs(&self) -> Vec<&Utf8Path> {
+        iter::once(self.config_dir())
+            .chain(self.system_config_dirs().iter().map(Utf8PathBuf::as_path))
+            .collect()
+    }
+
+    /// Find the config file with the given relative name in the [`config_dirs`](Self::config_dirs)
+    /// and return the one with the highest priority.
+    fn find_config_file(&self, name: &str) -> Option<Utf8PathBuf> {
+        self.config_dirs()
+            .into_iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Find all config files with the given relative name in the
+    /// [`config_dirs`](Self::config_dirs), from highest to lowest priority.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tempfile::TempDir;
+    /// use unidirs::{Directories, ServiceDirs, Utf8Path};
+    ///
+    /// let temp = TempDir::new().unwrap();
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .prefix(Utf8Path::from_path(temp.path()).unwrap())
+    ///     .build();
+    ///
+    /// for dir in dirs.config_dirs() {
+    ///     std::fs::create_dir_all(dir).unwrap();
+    ///     std::fs::write(dir.join("app.toml"), "").unwrap();
+    /// }
+    ///
+    /// let files = dirs.find_all_config_files("app.toml");
+    ///
+    /// assert_eq!(2, files.len());
+    /// assert_eq!(Some(&files[0]), dirs.find_config_file("app.toml").as_ref());
+    /// ```
+    fn find_all_config_files(&self, name: &str) -> Vec<Utf8PathBuf> {
+        self.config_dirs()
+            .into_iter()
+            .map(|dir| dir.join(name))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Additional read-only data directories with a lower priority than the
+    /// [`data_dir`](Self::data_dir), ordered from highest to lowest priority. These usually hold
+    /// assets like templates or plugins, that are installed by a package.
+    ///
+    /// Returns an empty list if the backend has no such directories.
+    fn system_data_dirs(&self) -> &[Utf8PathBuf] {
+        &[]
+    }
+
+    /// All data directories, starting with the [`data_dir`](Self::data_dir) and followed by the
+    /// [`system_data_dirs`](Self::system_data_dirs), from highest to lowest priority.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::new("example", "app");
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!(
+    ///     vec!["/var/lib/app", "/usr/local/share/app", "/usr/share/app"],
+    ///     dirs.data_dirs(),
+    /// );
+    /// ```
+    fn data_dirs(&self) -> Vec<&Utf8Path> {
+        iter::once(self.data_dir())
+            .chain(self.system_data_dirs().iter().map(Utf8PathBuf::as_path))
+            .collect()
+    }
+
+    /// Find the data file with the given relative name in the [`data_dirs`](Self::data_dirs) and
+    /// return the one with the highest priority.
+    fn find_data_file(&self, name: &str) -> Option<Utf8PathBuf> {
+        self.data_dirs()
+            .into_iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// List the entries of the given subdirectory across all [`data_dirs`](Self::data_dirs),
+    /// sorted by name. If the same name exists in several directories, only the entry with the
+    /// highest priority is returned. Directories that don't exist are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the directories can't be read or contains a name that is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tempfile::TempDir;
+    /// use unidirs::{Directories, ServiceDirs, Utf8Path};
+    ///
+    /// let temp = TempDir::new().unwrap();
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .prefix(Utf8Path::from_path(temp.path()).unwrap())
+    ///     .build();
+    ///
+    /// let user = dirs.data_dir().join("templates");
+    /// let system = dirs.system_data_dirs()[0].join("templates");
+    /// std::fs::create_dir_all(&user).unwrap();
+    /// std::fs::create_dir_all(&system).unwrap();
+    /// std::fs::write(user.join("a.tpl"), "").unwrap();
+    /// std::fs::write(system.join("a.tpl"), "").unwrap();
+    /// std::fs::write(system.join("b.tpl"), "").unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![user.join("a.tpl"), system.join("b.tpl")],
+    ///     dirs.data_dir_entries("templates").unwrap(),
+    /// );
+    /// ```
+    fn data_dir_entries(&self, subdir: &str) -> Result<Vec<Utf8PathBuf>, Error> {
+        search::data_dir_entries(self, subdir)
+    }
+
+    /// The Unix permission mode that is applied to a directory of the given kind, when it is
+    /// created by [`create_all`](Self::create_all). Defaults to `0o700`, giving access to the
+    /// owner only. The value is ignored for the runtime directory, which always uses `0o700`, and
+    /// on non-Unix platforms.
+    fn dir_mode(&self, kind: DirectoryKind) -> u32 {
+        let _ = kind;
+        0o700
+    }
+
+    /// Whether an existing directory of the given kind may be owned by `root` instead of the
+    /// current user, when verified by [`create_all`](Self::create_all). Defaults to `false`, as
+    /// the current user usually can't write to such a directory. The value is ignored for the
+    /// runtime directory, which must always be owned by the current user.
+    fn root_owned(&self, kind: DirectoryKind) -> bool {
+        let _ = kind;
+        false
+    }
+
+    /// Create all directories that don't exist yet, including any missing parents, and apply the
+    /// permissions as defined by [`dir_mode`](Self::dir_mode). The kinds of directories that were
+    /// newly created are returned. Missing parents are created with the default permissions, so
+    /// that more permissive directories within them stay reachable.
+    ///
+    /// Directories that already exist are checked for their safety instead. On Unix systems they
+    /// must neither be writable by everyone, nor be owned by another user than the current one
+    /// (or `root`, if allowed by [`root_owned`](Self::root_owned)). The runtime directory must
+    /// additionally not be accessible by the group or others.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the directories can't be created, or an existing one is not a directory or
+    /// fails the above safety checks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tempfile::TempDir;
+    /// use unidirs::{Directories, DirectoryKind, LocalDirs, Utf8Path};
+    ///
+    /// let temp = TempDir::new().unwrap();
+    /// let dirs = LocalDirs::new_at(Utf8Path::from_path(temp.path()).unwrap());
+    ///
+    /// assert_eq!(DirectoryKind::ALL.to_vec(), dirs.create_all().unwrap());
+    /// assert!(dirs.create_all().unwrap().is_empty());
+    /// ```
+    ///
+    /// Existing directories that are writable by everyone are refused:
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// use std::{fs, os::unix::fs::PermissionsExt};
+    ///
+    /// use tempfile::TempDir;
+    /// use unidirs::{Directories, Error, LocalDirs, Utf8Path};
+    ///
+    /// let temp = TempDir::new().unwrap();
+    /// let dirs = LocalDirs::new_at(Utf8Path::from_path(temp.path()).unwrap());
+    ///
+    /// fs::create_dir(dirs.config_dir()).unwrap();
+    /// fs::set_permissions(dirs.config_dir(), fs::Permissions::from_mode(0o777)).unwrap();
+    ///
+    /// assert!(matches!(
+    ///     dirs.create_all(),
+    ///     Err(Error::InsecurePermissions { mode: 0o777, .. }),
+    /// ));
+    /// # }
+    /// ```
+    ///
+    /// As are existing directories that belong to another user (changing the owner requires
+    /// `root`):
+    ///
+    /// ```rust
+    /// # #[cfg(unix)] {
+    /// # if !rustix::process::geteuid().is_root() {
+    /// #     return;
+    /// # }
+    /// use std::{fs, os::unix::fs::chown};
+    ///
+    /// use tempfile::TempDir;
+    /// use unidirs::{Directories, Error, LocalDirs, Utf8Path};
+    ///
+    /// let temp = TempDir::new().unwrap();
+    /// let dirs = LocalDirs::new_at(Utf8Path::from_path(temp.path()).unwrap());
+    ///
+    /// fs::create_dir(dirs.config_dir()).unwrap();
+    /// chown(dirs.config_dir(), Some(65534), None).unwrap();
+    ///
+    /// assert!(matches!(
+    ///     dirs.create_all(),
+    ///     Err(Error::ForeignOwner { uid: 65534, .. }),
+    /// ));
+    /// # }
+    /// ```
+    fn create_all(&self) -> Result<Vec<DirectoryKind>, Error> {
+        create::create_all(self)
+    }
+}
+
+/// The same directories as [`Directories`], but as standard paths that don't need to be valid
+/// UTF-8. All types that implement [`Directories`] implement this trait as well, so it can be used
+/// to abstract over the UTF-8 backends and their fallbacks like [`OsUserDirs`].
+///
+/// # Examples
+///
+/// ```rust
+/// use unidirs::{LocalDirs, OsDirectories};
+///
+/// let dirs = LocalDirs::new_at("/app");
+///
+/// assert_eq!(std::path::Path::new("/app/config"), dirs.config_path());
+/// ```
+pub trait OsDirectories {
+    /// See [`Directories::cache_dir`].
+    fn cache_path(&self) -> &Path;
+
+    /// See [`Directories::config_dir`].
+    fn config_path(&self) -> &Path;
+
+    /// See [`Directories::data_dir`].
+    fn data_path(&self) -> &Path;
+
+    /// See [`Directories::state_dir`].
+    fn state_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// See [`Directories::runtime_dir`].
+    fn runtime_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// See [`Directories::log_dir`].
+    fn log_path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Get the directory of the given kind, dispatching to the matching `*_path` function.
+    fn path(&self, kind: DirectoryKind) -> Option<&Path> {
+        match kind {
+            DirectoryKind::Cache => Some(self.cache_path()),
+            DirectoryKind::Config => Some(self.config_path()),
+            DirectoryKind::Data => Some(self.data_path()),
+            DirectoryKind::State => self.state_path(),
+            DirectoryKind::Runtime => self.runtime_path(),
+            DirectoryKind::Log => self.log_path(),
+        }
+    }
+}
+
+impl<D: Directories + ?Sized> OsDirectories for D {
+    fn cache_path(&self) -> &Path {
+        self.cache_dir().as_std_path()
+    }
+
+    fn config_path(&self) -> &Path {
+        self.config_dir().as_std_path()
+    }
+
+    fn data_path(&self) -> &Path {
+        self.data_dir().as_std_path()
+    }
+
+    fn state_path(&self) -> Option<&Path> {
+        self.state_dir().map(Utf8Path::as_std_path)
+    }
+
+    fn runtime_path(&self) -> Option<&Path> {
+        self.runtime_dir().map(Utf8Path::as_std_path)
+    }
+
+    fn log_path(&self) -> Option<&Path> {
+        self.log_dir().map(Utf8Path::as_std_path)
+    }
+}
+
+/// The different kinds of directories provided by the [`Directories`] trait.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DirectoryKind {
+    /// See [`Directories::cache_dir`].
+    Cache,
+    /// See [`Directories::config_dir`].
+    Config,
+    /// See [`Directories::data_dir`].
+    Data,
+    /// See [`Directories::state_dir`].
+    State,
+    /// See [`Directories::runtime_dir`].
+    Runtime,
+    /// See [`Directories::log_dir`].
+    Log,
+}
+
+impl Display for DirectoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cache => "cache",
+            Self::Config => "config",
+            Self::Data => "data",
+            Self::State => "state",
+            Self::Runtime => "runtime",
+            Self::Log => "log",
+        })
+    }
+}
+
+impl DirectoryKind {
+    /// All directory kinds, in the order of their declaration.
+    pub const ALL: [Self; 6] = [
+        Self::Cache,
+        Self::Config,
+        Self::Data,
+        Self::State,
+        Self::Runtime,
+        Self::Log,
+    ];
 }
@@ -1,33 +1,43 @@
-use std::env;
-
 use camino::{Utf8Path, Utf8PathBuf};
 
-use crate::Directories;
+use crate::{error, Directories, DirectoryKind, Environment, Error, HostEnv};
 
 /// Local directories are meant mostly for debug purposes while developing an application. By
 /// default it provides all available directories under a `.local` folder in the current working
 /// directory.
 ///
-/// An alternative base directory can be provided with the [`LocalDirs::new_at`] function.
+/// An alternative base directory can be provided with the [`LocalDirs::new_at`] function, or
+/// discovered from the project's root directory with [`LocalDirs::discover`].
 ///
 /// The folders are defined as follows, with `<base>` being either `.local` or a user defined
 /// directory:
 ///
-/// | Type   | Location        |
-/// | ------ | --------------- |
-/// | Cache  | `<base>`/cache  |
-/// | Config | `<base>`/config |
-/// | Data   | `<base>`/data   |
+/// | Type    | Location         |
+/// | ------- | ---------------- |
+/// | Cache   | `<base>`/cache   |
+/// | Config  | `<base>`/config  |
+/// | Data    | `<base>`/data    |
+/// | State   | `<base>`/state   |
+/// | Runtime | `<base>`/runtime |
+/// | Log     | `<base>`/log     |
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::struct_field_names)]
 pub struct LocalDirs {
     cache_dir: Utf8PathBuf,
     config_dir: Utf8PathBuf,
     data_dir: Utf8PathBuf,
+    state_dir: Utf8PathBuf,
+    runtime_dir: Utf8PathBuf,
+    log_dir: Utf8PathBuf,
 }
 
 impl LocalDirs {
     /// Create a default instance, using the `$PWD/.local` directory as a base.
     ///
+    /// # Errors
+    ///
+    /// Fails if the current working directory can't be determined or is not valid UTF-8.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -38,12 +48,135 @@ impl LocalDirs {
     ///
     /// assert_eq!(env::current_dir().unwrap().join(".local/data"), dirs.data_dir());
     /// ```
-    #[must_use]
-    pub fn new() -> Option<Self> {
-        let base = env::current_dir().ok()?;
-        let base = Utf8PathBuf::from_path_buf(base).ok()?.join(".local");
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(&HostEnv)
+    }
 
-        Some(Self::new_at(base))
+    /// Same as [`LocalDirs::new`], but use the working directory of the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the current working directory can't be determined or is not valid UTF-8.
+    pub fn new_in(env: &dyn Environment) -> Result<Self, Error> {
+        let base = env.current_dir().map_err(Error::CurrentDir)?;
+        let base = error::utf8(&base)?.join(".local");
+
+        Ok(Self::new_at(base))
+    }
+
+    /// Create an instance, using the `.local` directory in the root of the current project as
+    /// base. This avoids scattering `.local` folders across a project when run from different
+    /// working directories, like in a Cargo workspace.
+    ///
+    /// The root is searched by walking up from the current working directory, until one of the
+    /// following markers is found:
+    ///
+    /// - A `Cargo.toml` file that defines a `[workspace]`.
+    /// - A `.git` folder or file.
+    ///
+    /// The search stops at the user's home directory, which is never considered a project root.
+    ///
+    /// The `CARGO_WORKSPACE_DIR` environment variable is used directly as root, if set. Otherwise,
+    /// the `CARGO_MANIFEST_DIR` variable is used as starting point instead of the working
+    /// directory, if set. If no root is found, the starting point is used like in
+    /// [`LocalDirs::new`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the current working directory can't be determined or is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, LocalDirs};
+    ///
+    /// let dirs = LocalDirs::discover().unwrap();
+    ///
+    /// println!("data_dir = {}", dirs.data_dir());
+    /// ```
+    pub fn discover() -> Result<Self, Error> {
+        Self::discover_in::<_, &str>(&HostEnv, [])
+    }
+
+    /// Same as [`LocalDirs::discover`], but additionally look for any of the given file or folder
+    /// names to identify the project root. These are checked before the default markers in each
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the current working directory can't be determined or is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, LocalDirs};
+    ///
+    /// let dirs = LocalDirs::discover_with([".project-root"]).unwrap();
+    ///
+    /// println!("data_dir = {}", dirs.data_dir());
+    /// ```
+    pub fn discover_with<I, S>(markers: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::discover_in(&HostEnv, markers)
+    }
+
+    /// Same as [`LocalDirs::discover_with`], but use the variables, working directory and file
+    /// system of the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the current working directory can't be determined or is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, LocalDirs, MockEnv};
+    ///
+    /// let env = MockEnv::new().with_var("CARGO_WORKSPACE_DIR", "/workspace");
+    /// let dirs = LocalDirs::discover_in::<_, &str>(&env, []).unwrap();
+    ///
+    /// assert_eq!("/workspace/.local/data", dirs.data_dir());
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/project/src")
+    ///     .with_dir("/project/.git");
+    /// let dirs = LocalDirs::discover_in::<_, &str>(&env, []).unwrap();
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!("/project/.local/data", dirs.data_dir());
+    /// ```
+    pub fn discover_in<I, S>(env: &dyn Environment, markers: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let markers = markers.into_iter().collect::<Vec<_>>();
+        let markers = markers.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+
+        if let Some(root) = env.var_os("CARGO_WORKSPACE_DIR") {
+            let root = Utf8PathBuf::from_path_buf(root.into()).map_err(Error::NonUtf8Path)?;
+            return Ok(Self::new_at(root.join(".local")));
+        }
+
+        let start = match env.var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => dir.into(),
+            None => env.current_dir().map_err(Error::CurrentDir)?,
+        };
+        let start = error::utf8(&start)?;
+        let home = env.home_dir();
+
+        // The home directory is never a project root, even if it contains a `.git` folder, like
+        // a repository for dotfiles. Otherwise, the directories would end up in `~/.local`.
+        let root = start
+            .ancestors()
+            .take_while(|dir| home.as_deref() != Some(dir.as_std_path()))
+            .find(|dir| is_root(env, dir, &markers))
+            .unwrap_or(start);
+
+        Ok(Self::new_at(root.join(".local")))
     }
 
     /// Create an instance at the given base directory.
@@ -58,6 +191,7 @@ impl LocalDirs {
     /// let dirs = LocalDirs::new_at(Utf8Path::from_path(temp.path()).unwrap());
     ///
     /// assert_eq!(temp.path().join("data"), dirs.data_dir());
+    /// assert_eq!(temp.path().join("log"), dirs.log_dir().unwrap());
     /// ```
     pub fn new_at(base: impl AsRef<Utf8Path>) -> Self {
         fn inner(base: &Utf8Path) -> LocalDirs {
@@ -65,11 +199,45 @@ impl LocalDirs {
                 cache_dir: base.join("cache"),
                 config_dir: base.join("config"),
                 data_dir: base.join("data"),
+                state_dir: base.join("state"),
+                runtime_dir: base.join("runtime"),
+                log_dir: base.join("log"),
             }
         }
 
         inner(base.as_ref())
     }
+
+    /// Replace the location of a single directory.
+    pub(crate) fn set_dir(&mut self, kind: DirectoryKind, path: Utf8PathBuf) {
+        match kind {
+            DirectoryKind::Cache => self.cache_dir = path,
+            DirectoryKind::Config => self.config_dir = path,
+            DirectoryKind::Data => self.data_dir = path,
+            DirectoryKind::State => self.state_dir = path,
+            DirectoryKind::Runtime => self.runtime_dir = path,
+            DirectoryKind::Log => self.log_dir = path,
+        }
+    }
+}
+
+/// Check whether the given directory is the root of a project.
+fn is_root(env: &dyn Environment, dir: &Utf8Path, markers: &[&str]) -> bool {
+    let exists = |name: &str| {
+        let path = dir.join(name);
+        env.is_file(path.as_std_path()) || env.is_dir(path.as_std_path())
+    };
+
+    markers.iter().any(|marker| exists(marker))
+        || env
+            .read_to_string(dir.join("Cargo.toml").as_std_path())
+            .is_ok_and(|manifest| {
+                manifest.lines().any(|line| {
+                    let line = line.trim();
+                    line == "[workspace]" || line.starts_with("[workspace.")
+                })
+            })
+        || exists(".git")
 }
 
 impl Directories for LocalDirs {
@@ -84,4 +252,16 @@ impl Directories for LocalDirs {
     fn data_dir(&self) -> &Utf8Path {
         &self.data_dir
     }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        Some(&self.state_dir)
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        Some(&self.runtime_dir)
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        Some(&self.log_dir)
+    }
 }
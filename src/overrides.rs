@@ -0,0 +1,242 @@
+use std::fmt::{self, Display};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{heuristic, Directories, DirectoryKind, Environment, Error, HostEnv};
+
+/// A directory that was relocated through an environment variable.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Override {
+    kind: DirectoryKind,
+    var: String,
+    path: Utf8PathBuf,
+}
+
+impl Override {
+    /// The kind of directory that was relocated.
+    #[must_use]
+    pub fn kind(&self) -> DirectoryKind {
+        self.kind
+    }
+
+    /// The name of the environment variable that provided the location.
+    #[must_use]
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    /// The new location of the directory.
+    #[must_use]
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} directory at {}", self.var, self.kind, self.path)
+    }
+}
+
+/// Read all directory overrides for the given variable prefix from the environment.
+pub(crate) fn from_env(env: &dyn Environment, prefix: &str) -> Result<Vec<Override>, Error> {
+    DirectoryKind::ALL
+        .into_iter()
+        .filter_map(|kind| {
+            let var = format!("{prefix}_{}_DIR", var_name(kind));
+            let value = env.var_os(&var).filter(|value| !value.is_empty())?;
+
+            Some(match Utf8PathBuf::from_path_buf(value.into()) {
+                Ok(path) if path.has_root() => Ok(Override { kind, var, path }),
+                Ok(path) => Err(Error::RelativePath { var, path }),
+                Err(path) => Err(Error::NonUtf8Path(path)),
+            })
+        })
+        .collect()
+}
+
+fn var_name(kind: DirectoryKind) -> &'static str {
+    match kind {
+        DirectoryKind::Cache => "CACHE",
+        DirectoryKind::Config => "CONFIG",
+        DirectoryKind::Data => "DATA",
+        DirectoryKind::State => "STATE",
+        DirectoryKind::Runtime => "RUNTIME",
+        DirectoryKind::Log => "LOG",
+    }
+}
+
+/// Override directories wrap any other [`Directories`] implementation and replace single
+/// directories with locations given through environment variables. This allows to relocate only
+/// a specific directory, like putting the cache on a `tmpfs`, without changing the whole layout.
+///
+/// The variables are named `<PREFIX>_<KIND>_DIR`, where the prefix defaults to the application
+/// name in uppercase, with any characters other than ASCII letters and digits replaced by an
+/// underscore:
+///
+/// | Type    | Variable               |
+/// | ------- | ---------------------- |
+/// | Cache   | `<PREFIX>_CACHE_DIR`   |
+/// | Config  | `<PREFIX>_CONFIG_DIR`  |
+/// | Data    | `<PREFIX>_DATA_DIR`    |
+/// | State   | `<PREFIX>_STATE_DIR`   |
+/// | Runtime | `<PREFIX>_RUNTIME_DIR` |
+/// | Log     | `<PREFIX>_LOG_DIR`     |
+///
+/// Empty variables are ignored, while relative paths are rejected, like the directory variables
+/// of systemd. The applied overrides can be retrieved with [`overrides`](Self::overrides), to log
+/// them at startup.
+///
+/// When using the [`SimpleBuilder`](crate::SimpleBuilder), the same overrides can be enabled with
+/// [`SimpleBuilder::env_overrides`](crate::SimpleBuilder::env_overrides) instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use unidirs::{Directories, MockEnv, OverrideDirs, UnifiedDirs};
+///
+/// let env = MockEnv::new().with_var("MY_APP_CACHE_DIR", "/tmp/cache");
+/// let dirs = OverrideDirs::new_in(&env, UnifiedDirs::local_at("/app"), "my-app").unwrap();
+///
+/// assert_eq!("/tmp/cache", dirs.cache_dir());
+/// assert_eq!("/app/data", dirs.data_dir());
+/// assert_eq!("MY_APP_CACHE_DIR", dirs.overrides()[0].var());
+/// ```
+///
+/// Relative paths would depend on the working directory the application is started in:
+///
+/// ```rust
+/// use unidirs::{Error, MockEnv, OverrideDirs, UnifiedDirs};
+///
+/// let env = MockEnv::new().with_var("MY_APP_CACHE_DIR", "tmp/cache");
+///
+/// assert!(matches!(
+///     OverrideDirs::new_in(&env, UnifiedDirs::local_at("/app"), "my-app"),
+///     Err(Error::RelativePath { .. }),
+/// ));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OverrideDirs<D> {
+    inner: D,
+    overrides: Vec<Override>,
+}
+
+impl<D: Directories> OverrideDirs<D> {
+    /// Wrap the given directories, using the application name to derive the variable prefix.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the variables is not valid UTF-8 or not an absolute path.
+    pub fn new(inner: D, application: impl AsRef<str>) -> Result<Self, Error> {
+        Self::with_prefix(inner, heuristic::env_prefix(application.as_ref()))
+    }
+
+    /// Wrap the given directories, using the given variable prefix as is.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the variables is not valid UTF-8 or not an absolute path.
+    pub fn with_prefix(inner: D, prefix: impl AsRef<str>) -> Result<Self, Error> {
+        Self::with_prefix_in(&HostEnv, inner, prefix)
+    }
+
+    /// Same as [`OverrideDirs::new`], but read the variables from the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the variables is not valid UTF-8 or not an absolute path.
+    pub fn new_in(
+        env: &dyn Environment,
+        inner: D,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::with_prefix_in(env, inner, heuristic::env_prefix(application.as_ref()))
+    }
+
+    /// Same as [`OverrideDirs::with_prefix`], but read the variables from the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the variables is not valid UTF-8 or not an absolute path.
+    pub fn with_prefix_in(
+        env: &dyn Environment,
+        inner: D,
+        prefix: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            inner,
+            overrides: from_env(env, prefix.as_ref())?,
+        })
+    }
+
+    /// The overrides that were found in the environment.
+    #[must_use]
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+
+    /// Access the wrapped directories, without any overrides applied.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Unwrap the inner directories, dropping the overrides.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn get(&self, kind: DirectoryKind) -> Option<&Utf8Path> {
+        self.overrides
+            .iter()
+            .find(|o| o.kind == kind)
+            .map(|o| o.path.as_path())
+    }
+}
+
+impl<D: Directories> Directories for OverrideDirs<D> {
+    fn cache_dir(&self) -> &Utf8Path {
+        self.get(DirectoryKind::Cache)
+            .unwrap_or_else(|| self.inner.cache_dir())
+    }
+
+    fn config_dir(&self) -> &Utf8Path {
+        self.get(DirectoryKind::Config)
+            .unwrap_or_else(|| self.inner.config_dir())
+    }
+
+    fn data_dir(&self) -> &Utf8Path {
+        self.get(DirectoryKind::Data)
+            .unwrap_or_else(|| self.inner.data_dir())
+    }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        self.get(DirectoryKind::State)
+            .or_else(|| self.inner.state_dir())
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.get(DirectoryKind::Runtime)
+            .or_else(|| self.inner.runtime_dir())
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        self.get(DirectoryKind::Log)
+            .or_else(|| self.inner.log_dir())
+    }
+
+    fn system_config_dirs(&self) -> &[Utf8PathBuf] {
+        self.inner.system_config_dirs()
+    }
+
+    fn system_data_dirs(&self) -> &[Utf8PathBuf] {
+        self.inner.system_data_dirs()
+    }
+
+    fn dir_mode(&self, kind: DirectoryKind) -> u32 {
+        self.inner.dir_mode(kind)
+    }
+
+    fn root_owned(&self, kind: DirectoryKind) -> bool {
+        self.inner.root_owned(kind)
+    }
+}
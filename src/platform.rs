@@ -0,0 +1,123 @@
+use std::{
+    ffi::OsStr,
+    fmt::{self, Display},
+    path::PathBuf,
+};
+
+use camino::Utf8PathBuf;
+
+/// Operating systems that directory layouts can be computed for.
+///
+/// The layouts don't depend on the system the code is compiled for. Therefore, it's possible to
+/// compute the directories of another platform, for example to prepare an installer or verify the
+/// paths of all platforms in a test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Platform {
+    /// Linux, including Android.
+    Linux,
+    /// Mac OS, including iOS.
+    MacOs,
+    /// Windows.
+    Windows,
+    /// FreeBSD.
+    FreeBsd,
+    /// NetBSD.
+    NetBsd,
+    /// OpenBSD.
+    OpenBsd,
+    /// Any other Unix system that follows the XDG conventions.
+    Unix,
+    /// Any other platform without known directory conventions, like WebAssembly. User directories
+    /// are not available, while service directories use the Unix layout, which is usually only
+    /// meaningful with a custom [`root`](crate::ServiceDirsBuilder::root) or
+    /// [`prefix`](crate::ServiceDirsBuilder::prefix).
+    Unknown,
+}
+
+impl Platform {
+    /// The platform that this code is compiled for.
+    #[must_use]
+    pub const fn current() -> Self {
+        if cfg!(any(target_os = "linux", target_os = "android")) {
+            Self::Linux
+        } else if cfg!(any(target_os = "macos", target_os = "ios")) {
+            Self::MacOs
+        } else if cfg!(windows) {
+            Self::Windows
+        } else if cfg!(target_os = "freebsd") {
+            Self::FreeBsd
+        } else if cfg!(target_os = "netbsd") {
+            Self::NetBsd
+        } else if cfg!(target_os = "openbsd") {
+            Self::OpenBsd
+        } else if cfg!(unix) {
+            Self::Unix
+        } else {
+            Self::Unknown
+        }
+    }
+
+    /// Whether the platform uses the XDG base directories for user directories.
+    pub(crate) const fn is_xdg(self) -> bool {
+        matches!(
+            self,
+            Self::Linux | Self::FreeBsd | Self::NetBsd | Self::OpenBsd | Self::Unix
+        )
+    }
+
+    /// The path separator of the platform.
+    pub(crate) const fn separator(self) -> char {
+        match self {
+            Self::Windows => '\\',
+            _ => '/',
+        }
+    }
+
+    /// Join the given components to the base path, using the platform's separator regardless of
+    /// the host system.
+    pub(crate) fn join(self, base: &str, components: &[&str]) -> Utf8PathBuf {
+        let separator = self.separator();
+        let mut path = base.to_owned();
+
+        for component in components {
+            if !path.ends_with(separator) {
+                path.push(separator);
+            }
+            path.push_str(component.trim_start_matches(separator));
+        }
+
+        path.into()
+    }
+
+    /// Like [`join`](Self::join), but for a base path that may not be valid UTF-8.
+    pub(crate) fn join_os(self, base: &OsStr, components: &[&str]) -> PathBuf {
+        let separator = self.separator();
+        let mut path = base.to_owned();
+
+        for component in components {
+            // Invalid UTF-8 is replaced, but the separator itself is always preserved.
+            if !path.to_string_lossy().ends_with(separator) {
+                path.push(separator.encode_utf8(&mut [0; 4]));
+            }
+            path.push(component.trim_start_matches(separator));
+        }
+
+        path.into()
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Linux => "linux",
+            Self::MacOs => "macos",
+            Self::Windows => "windows",
+            Self::FreeBsd => "freebsd",
+            Self::NetBsd => "netbsd",
+            Self::OpenBsd => "openbsd",
+            Self::Unix => "unix",
+            Self::Unknown => "unknown",
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use std::io;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{error, Directories, DirectoryKind, Environment, Error, HostEnv};
+
+/// Portable directories are used for applications that carry all their files with them, for
+/// example when distributed on a USB stick or as a zip archive. All directories are located next
+/// to the application's executable.
+///
+/// An alternative base directory can be provided with the [`PortableDirs::new_at`] function.
+///
+/// The folders are defined as follows, with `<base>` being either the folder containing the
+/// executable or a user defined directory:
+///
+/// | Type    | Location        |
+/// | ------- | --------------- |
+/// | Cache   | `<base>`/cache  |
+/// | Config  | `<base>`/config |
+/// | Data    | `<base>`/data   |
+/// | State   | `<base>`/state  |
+/// | Runtime | _none_          |
+/// | Log     | `<base>`/log    |
+///
+/// There is no runtime directory, as removable media often lack support for special files like
+/// sockets.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::struct_field_names)]
+pub struct PortableDirs {
+    cache_dir: Utf8PathBuf,
+    config_dir: Utf8PathBuf,
+    data_dir: Utf8PathBuf,
+    state_dir: Utf8PathBuf,
+    runtime_dir: Option<Utf8PathBuf>,
+    log_dir: Utf8PathBuf,
+}
+
+impl PortableDirs {
+    /// Create a default instance, using the directory of the current executable as a base.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path of the current executable can't be determined or is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{env, io};
+    /// use unidirs::{Directories, PortableDirs};
+    ///
+    /// let dirs = PortableDirs::new().unwrap();
+    /// let exe = env::current_exe().unwrap();
+    ///
+    /// assert_eq!(exe.parent().unwrap().join("data"), dirs.data_dir());
+    /// ```
+    pub fn new() -> Result<Self, Error> {
+        Self::new_in(&HostEnv)
+    }
+
+    /// Same as [`PortableDirs::new`], but use the executable path of the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path of the executable can't be determined or is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, MockEnv, PortableDirs};
+    ///
+    /// let env = MockEnv::new().with_current_exe("/media/usb/app/app");
+    /// let dirs = PortableDirs::new_in(&env).unwrap();
+    ///
+    /// assert_eq!("/media/usb/app/data", dirs.data_dir());
+    /// ```
+    pub fn new_in(env: &dyn Environment) -> Result<Self, Error> {
+        let exe = env.current_exe().map_err(Error::CurrentExe)?;
+        let base = error::utf8(&exe)?.parent().ok_or_else(|| {
+            Error::CurrentExe(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the executable has no parent directory",
+            ))
+        })?;
+
+        Ok(Self::new_at(base))
+    }
+
+    /// Create an instance at the given base directory.
+    pub fn new_at(base: impl AsRef<Utf8Path>) -> Self {
+        fn inner(base: &Utf8Path) -> PortableDirs {
+            PortableDirs {
+                cache_dir: base.join("cache"),
+                config_dir: base.join("config"),
+                data_dir: base.join("data"),
+                state_dir: base.join("state"),
+                runtime_dir: None,
+                log_dir: base.join("log"),
+            }
+        }
+
+        inner(base.as_ref())
+    }
+
+    /// Replace the location of a single directory.
+    pub(crate) fn set_dir(&mut self, kind: DirectoryKind, path: Utf8PathBuf) {
+        match kind {
+            DirectoryKind::Cache => self.cache_dir = path,
+            DirectoryKind::Config => self.config_dir = path,
+            DirectoryKind::Data => self.data_dir = path,
+            DirectoryKind::State => self.state_dir = path,
+            DirectoryKind::Runtime => self.runtime_dir = Some(path),
+            DirectoryKind::Log => self.log_dir = path,
+        }
+    }
+}
+
+impl Directories for PortableDirs {
+    fn cache_dir(&self) -> &Utf8Path {
+        &self.cache_dir
+    }
+
+    fn config_dir(&self) -> &Utf8Path {
+        &self.config_dir
+    }
+
+    fn data_dir(&self) -> &Utf8Path {
+        &self.data_dir
+    }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        Some(&self.state_dir)
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        Some(&self.log_dir)
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fs, io,
+};
+
+use camino::Utf8PathBuf;
+
+use crate::{error, Directories, Error};
+
+pub(crate) fn data_dir_entries<D>(dirs: &D, subdir: &str) -> Result<Vec<Utf8PathBuf>, Error>
+where
+    D: Directories + ?Sized,
+{
+    let mut entries = BTreeMap::new();
+
+    for dir in dirs.data_dirs() {
+        let dir = dir.join(subdir);
+        let io_err = |source| Error::Io {
+            path: dir.clone(),
+            source,
+        };
+
+        let read_dir = match fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(io_err(e)),
+        };
+
+        for entry in read_dir {
+            let path = entry.map_err(io_err)?.path();
+            let path = error::utf8(&path)?;
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+
+            // Layers are visited by priority, so the first entry of each name wins.
+            if let Entry::Vacant(entry) = entries.entry(name.to_owned()) {
+                entry.insert(path.to_owned());
+            }
+        }
+    }
+
+    Ok(entries.into_values().collect())
+}
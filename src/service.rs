@@ -1,6 +1,8 @@
+use std::ffi::OsString;
+
 use camino::{Utf8Path, Utf8PathBuf};
 
-use crate::Directories;
+use crate::{Directories, DirectoryKind, Environment, HostEnv, MockEnv, Platform};
 
 /// Service directories are used for applications that run as a service (or often called daemon),
 /// usually run by a dedicated user account and controlled by the system rather than the user.
@@ -14,30 +16,71 @@ use crate::Directories;
 /// on Mac OS, the [`UserDirs`](crate::UserDirs) might be correct as well, but for system-run
 /// services the correct folders are the same as on other Unix systems.
 ///
-/// | Type   | Location           |
-/// | ------ | ------------------ |
-/// | Cache  | /var/cache/`<app>` |
-/// | Config | /etc/`<app>`       |
-/// | Data   | /var/lib/`<app>`   |
+/// Platforms without any directory conventions, like WebAssembly, use the same layout. Under WASI,
+/// these paths resolve against the directories that the host preopened for the module, so combine
+/// them with [`ServiceDirsBuilder::root`] or [`ServiceDirsBuilder::prefix`] as needed.
+///
+/// | Type    | Location           |
+/// | ------- | ------------------ |
+/// | Cache   | /var/cache/`<app>` |
+/// | Config  | /etc/`<app>`       |
+/// | Data    | /var/lib/`<app>`   |
+/// | State   | /var/lib/`<app>`   |
+/// | Runtime | /run/`<app>`       |
+/// | Log     | /var/log/`<app>`   |
+///
+/// Default configuration that is shipped with a package is additionally searched in the vendor
+/// directory /usr/lib/`<app>` (see [`Directories::system_config_dirs`]). Likewise, read-only assets
+/// that are installed by a package are searched in /usr/local/share/`<app>` and
+/// /usr/share/`<app>` (see [`Directories::system_data_dirs`]).
+///
+/// ### systemd
+///
+/// When run as a systemd unit with any of the `CacheDirectory=`, `ConfigurationDirectory=`,
+/// `StateDirectory=`, `RuntimeDirectory=` or `LogsDirectory=` settings, the actual locations are
+/// passed to the service in environment variables. These may differ from the above defaults, for
+/// example when `DynamicUser=` is enabled. Use [`ServiceDirs::with_systemd`] to prefer them.
 ///
 /// ## Windows
 ///
 /// On Windows there are three standard service accounts: `LocalService`, `NetworkService` and
 /// `LocalSystem`. These present different capabilities and a network service provides a middle
-/// ground with minimal capabilities plus networking access.
+/// ground with minimal capabilities plus networking access. Therefore, it is the default, but
+/// another account can be picked with [`ServiceDirsBuilder::account`]. The locations below are
+/// located in the `AppData` folder of the respective account, as listed in [`ServiceAccount`].
+///
+/// | Type    | Location                                                                           |
+/// | ------- | ---------------------------------------------------------------------------------- |
+/// | Cache   | C:\Windows\ServiceProfiles\NetworkService\AppData\Local\\`<org>`\\`<app>`\cache    |
+/// | Config  | C:\Windows\ServiceProfiles\NetworkService\AppData\Roaming\\`<org>`\\`<app>`\config |
+/// | Data    | C:\Windows\ServiceProfiles\NetworkService\AppData\Roaming\\`<org>`\\`<app>`\data   |
+/// | State   | _none_                                                                             |
+/// | Runtime | _none_                                                                             |
+/// | Log     | C:\Windows\ServiceProfiles\NetworkService\AppData\Local\\`<org>`\\`<app>`\log      |
 ///
-/// The API might be extended to pick the type of service account in the future.
+/// Alternatively, [`ServiceDirsBuilder::machine_wide`] puts all directories below the machine-wide
+/// `%ProgramData%` folder instead, independent of the account the service runs as. Its location is
+/// taken from the `ProgramData` variable (see [`ServiceDirsBuilder::environment`]) and falls back
+/// to C:\ProgramData if that is not set:
 ///
-/// | Type   | Location                                                                           |
-/// | ------ | ---------------------------------------------------------------------------------- |
-/// | Cache  | C:\Windows\ServiceProfiles\NetworkService\AppData\\`<org>`\\`<app>`\Local\cache    |
-/// | Config | C:\Windows\ServiceProfiles\NetworkService\AppData\\`<org>`\\`<app>`\Roaming\config |
-/// | Data   | C:\Windows\ServiceProfiles\NetworkService\AppData\\`<org>`\\`<app>`\Roaming\data   |
+/// | Type    | Location                                |
+/// | ------- | --------------------------------------- |
+/// | Cache   | C:\ProgramData\\`<org>`\\`<app>`\cache  |
+/// | Config  | C:\ProgramData\\`<org>`\\`<app>`\config |
+/// | Data    | C:\ProgramData\\`<org>`\\`<app>`\data   |
+/// | State   | _none_                                  |
+/// | Runtime | _none_                                  |
+/// | Log     | C:\ProgramData\\`<org>`\\`<app>`\log    |
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ServiceDirs {
     cache_dir: Utf8PathBuf,
     config_dir: Utf8PathBuf,
     data_dir: Utf8PathBuf,
+    state_dir: Option<Utf8PathBuf>,
+    runtime_dir: Option<Utf8PathBuf>,
+    log_dir: Option<Utf8PathBuf>,
+    system_config_dirs: Vec<Utf8PathBuf>,
+    system_data_dirs: Vec<Utf8PathBuf>,
 }
 
 impl ServiceDirs {
@@ -53,49 +96,553 @@ impl ServiceDirs {
     ///
     /// println!("data_dir = {}", dirs.data_dir());
     /// // On Unix:    /var/lib/app
-    /// // On Windows: C:\Windows\ServiceProfiles\NetworkService\AppData\example\app\data
+    /// // On Windows: C:\Windows\ServiceProfiles\NetworkService\AppData\Roaming\example\app\data
     /// ```
-    #[allow(unused_variables)]
     #[must_use]
     pub fn new(organization: impl AsRef<str>, application: impl AsRef<str>) -> Self {
-        fn inner(organization: &str, application: &str) -> ServiceDirs {
-            #[cfg(unix)]
-            {
-                ServiceDirs {
-                    cache_dir: Utf8PathBuf::from(format!("/var/cache/{application}")),
-                    config_dir: Utf8PathBuf::from(format!("/etc/{application}")),
-                    data_dir: Utf8PathBuf::from(format!("/var/lib/{application}")),
-                }
-            }
+        Self::builder(organization, application).build()
+    }
 
-            #[cfg(windows)]
-            {
-                let app_data = "C:\\Windows\\ServiceProfiles\\NetworkService\\AppData";
-                let project_dir = format!("{}/{}", organization, application);
-
-                ServiceDirs {
-                    cache_dir: Utf8PathBuf::from(format!(
-                        "{}\\Local\\{}\\cache",
-                        app_data, project_dir
-                    )),
-                    config_dir: Utf8PathBuf::from(format!(
-                        "{}\\Roaming\\{}\\config",
-                        app_data, project_dir
-                    )),
-                    data_dir: Utf8PathBuf::from(format!(
-                        "{}\\Roaming\\{}\\data",
-                        app_data, project_dir
-                    )),
-                }
-            }
+    /// Create a builder to further customize the locations of the directories, for example to
+    /// install an application into a different prefix than the file system root.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .prefix("/usr/local")
+    ///     .build();
+    ///
+    /// println!("config_dir = {}", dirs.config_dir());
+    /// // On Unix: /usr/local/etc/app
+    /// ```
+    pub fn builder<O, A>(organization: O, application: A) -> ServiceDirsBuilder<O, A>
+    where
+        O: AsRef<str>,
+        A: AsRef<str>,
+    {
+        ServiceDirsBuilder {
+            organization,
+            application,
+            platform: Platform::current(),
+            account: ServiceAccount::default(),
+            machine_wide: false,
+            env: None,
+            root: None,
+            prefix: None,
+            dirs: Vec::new(),
+        }
+    }
+
+    /// Create a new instance like [`ServiceDirs::new`], but prefer the directories that systemd
+    /// passes to a service through its environment variables. Any directory without a matching
+    /// variable falls back to the default location.
+    ///
+    /// | Type    | Variable                   |
+    /// | ------- | -------------------------- |
+    /// | Cache   | `$CACHE_DIRECTORY`         |
+    /// | Config  | `$CONFIGURATION_DIRECTORY` |
+    /// | Data    | `$STATE_DIRECTORY`         |
+    /// | State   | `$STATE_DIRECTORY`         |
+    /// | Runtime | `$RUNTIME_DIRECTORY`       |
+    /// | Log     | `$LOGS_DIRECTORY`          |
+    ///
+    /// If a unit defines multiple directories of the same type, the variable contains a
+    /// colon-separated list and the first entry is used. Values that are not valid UTF-8 or not
+    /// absolute paths are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::with_systemd("example", "app");
+    ///
+    /// println!("data_dir = {}", dirs.data_dir());
+    /// // Outside of systemd: /var/lib/app
+    /// // With `StateDirectory=app` and `DynamicUser=yes`: /var/lib/private/app
+    /// ```
+    #[must_use]
+    pub fn with_systemd(organization: impl AsRef<str>, application: impl AsRef<str>) -> Self {
+        Self::with_systemd_in(&HostEnv, organization, application)
+    }
+
+    /// Same as [`ServiceDirs::with_systemd`], but read the variables from the given environment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, MockEnv, ServiceDirs};
+    ///
+    /// let env = MockEnv::new().with_var("STATE_DIRECTORY", "/var/lib/private/app");
+    /// let dirs = ServiceDirs::with_systemd_in(&env, "example", "app");
+    ///
+    /// assert_eq!("/var/lib/private/app", dirs.data_dir());
+    /// ```
+    #[must_use]
+    pub fn with_systemd_in(
+        env: &dyn Environment,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Self {
+        Self::builder(organization, application)
+            .systemd_in(env)
+            .build()
+    }
+
+    /// Prepend the given root to all directories.
+    fn reroot(&mut self, platform: Platform, root: &Utf8Path) {
+        let dirs = [
+            &mut self.cache_dir,
+            &mut self.config_dir,
+            &mut self.data_dir,
+        ]
+        .into_iter()
+        .chain(self.state_dir.as_mut())
+        .chain(self.runtime_dir.as_mut())
+        .chain(self.log_dir.as_mut())
+        .chain(&mut self.system_config_dirs)
+        .chain(&mut self.system_data_dirs);
+
+        for path in dirs {
+            // Drive letters are dropped on Windows, as they can't be nested below another path.
+            let relative = match path.as_str().split_once(':') {
+                Some((drive, rest)) if platform == Platform::Windows && drive.len() == 1 => rest,
+                _ => path.as_str(),
+            };
+            *path = platform.join(root.as_str(), &[relative]);
+        }
+    }
+
+    /// Replace the location of a single directory.
+    pub(crate) fn set_dir(&mut self, kind: DirectoryKind, path: Utf8PathBuf) {
+        match kind {
+            DirectoryKind::Cache => self.cache_dir = path,
+            DirectoryKind::Config => self.config_dir = path,
+            DirectoryKind::Data => self.data_dir = path,
+            DirectoryKind::State => self.state_dir = Some(path),
+            DirectoryKind::Runtime => self.runtime_dir = Some(path),
+            DirectoryKind::Log => self.log_dir = Some(path),
+        }
+    }
+}
+
+/// Builder for [`ServiceDirs`], created through [`ServiceDirs::builder`].
+///
+/// The final location of each directory is determined as follows:
+///
+/// 1. If a directory was set explicitly with [`dir`](Self::dir) or passed by systemd (see
+///    [`systemd`](Self::systemd)), it is used as is.
+/// 2. Otherwise, if an installation prefix was set with [`prefix`](Self::prefix), the directory is
+///    located under it, following the GNU conventions (see below).
+/// 3. Otherwise, the default location as described in [`ServiceDirs`] is used.
+/// 4. Finally, if a root was set with [`root`](Self::root), it is prepended to every directory,
+///    like the `DESTDIR` variable of many build systems or a `chroot` environment.
+///
+/// With an installation prefix, the directories on Unix systems are:
+///
+/// | Type    | Location                     |
+/// | ------- | ---------------------------- |
+/// | Cache   | `<prefix>`/var/cache/`<app>` |
+/// | Config  | `<prefix>`/etc/`<app>`       |
+/// | Data    | `<prefix>`/var/lib/`<app>`   |
+/// | State   | `<prefix>`/var/lib/`<app>`   |
+/// | Runtime | `<prefix>`/var/run/`<app>`   |
+/// | Log     | `<prefix>`/var/log/`<app>`   |
+///
+/// The vendor configuration is searched in `<prefix>`/lib/`<app>` in that case.
+///
+/// On Windows, the prefix replaces the service account's `AppData` folder (or the `%ProgramData%`
+/// folder for a machine-wide layout). The root is prepended to the paths without their drive
+/// letter, so for example `C:\ProgramData` becomes `<root>\ProgramData`.
+#[derive(Clone, Debug)]
+pub struct ServiceDirsBuilder<O, A> {
+    organization: O,
+    application: A,
+    platform: Platform,
+    account: ServiceAccount,
+    machine_wide: bool,
+    env: Option<MockEnv>,
+    root: Option<Utf8PathBuf>,
+    prefix: Option<Utf8PathBuf>,
+    dirs: Vec<(DirectoryKind, Utf8PathBuf)>,
+}
+
+impl<O, A> ServiceDirsBuilder<O, A>
+where
+    O: AsRef<str>,
+    A: AsRef<str>,
+{
+    /// Set a root directory that is prepended to all paths. This is useful to prepare a file
+    /// system image for packaging, or run tests without elevated permissions. On Windows, the
+    /// drive letter of each path is dropped before the root is prepended.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, Platform, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .root("/tmp/image")
+    ///     .build();
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!("/tmp/image/var/lib/app", dirs.data_dir());
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .platform(Platform::Windows)
+    ///     .machine_wide()
+    ///     .root("D:\\image")
+    ///     .build();
+    ///
+    /// assert_eq!("D:\\image\\ProgramData\\example\\app\\data", dirs.data_dir());
+    /// ```
+    #[must_use]
+    pub fn root(mut self, root: impl Into<Utf8PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Compute the directories for the given platform instead of the current one. This allows to
+    /// prepare the layout of another system, for example when building an installer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, Platform, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .platform(Platform::Windows)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     "C:\\Windows\\ServiceProfiles\\NetworkService\\AppData\\Roaming\\example\\app\\data",
+    ///     dirs.data_dir(),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Pick the Windows service account that the service runs as. Defaults to
+    /// [`ServiceAccount::NetworkService`] and is ignored on other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, Platform, ServiceAccount, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .platform(Platform::Windows)
+    ///     .account(ServiceAccount::LocalSystem)
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     "C:\\Windows\\System32\\config\\systemprofile\\AppData\\Local\\example\\app\\cache",
+    ///     dirs.cache_dir(),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn account(mut self, account: ServiceAccount) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// Use the machine-wide `%ProgramData%` folder on Windows, instead of the service account's
+    /// `AppData` folder. This is ignored on other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, Platform, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .platform(Platform::Windows)
+    ///     .machine_wide()
+    ///     .build();
+    ///
+    /// assert_eq!("C:\\ProgramData\\example\\app\\config", dirs.config_dir());
+    /// ```
+    #[must_use]
+    pub fn machine_wide(mut self) -> Self {
+        self.machine_wide = true;
+        self
+    }
+
+    /// Read the variables from the given environment instead of the current process. This
+    /// covers the location of the machine-wide `%ProgramData%` folder and the systemd variables
+    /// (see [`systemd`](Self::systemd)), so it must be set before the latter. The variables are
+    /// captured immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, MockEnv, Platform, ServiceDirs};
+    ///
+    /// let env = MockEnv::new().with_var("ProgramData", "D:\\ProgramData");
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .platform(Platform::Windows)
+    ///     .environment(&env)
+    ///     .machine_wide()
+    ///     .build();
+    ///
+    /// assert_eq!("D:\\ProgramData\\example\\app\\config", dirs.config_dir());
+    /// ```
+    #[must_use]
+    pub fn environment(mut self, env: &dyn Environment) -> Self {
+        self.env = Some(
+            Self::VARS
+                .into_iter()
+                .filter_map(|name| Some((name, env.var_os(name)?)))
+                .fold(MockEnv::new(), |snapshot, (name, value)| {
+                    snapshot.with_var(name, value)
+                }),
+        );
+        self
+    }
+
+    /// Set the installation prefix, like `/usr/local` or `/opt/<app>`.
+    #[must_use]
+    pub fn prefix(mut self, prefix: impl Into<Utf8PathBuf>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Set the location of a single directory explicitly. The root is still prepended to it.
+    #[must_use]
+    pub fn dir(mut self, kind: DirectoryKind, path: impl Into<Utf8PathBuf>) -> Self {
+        self.dirs.push((kind, path.into()));
+        self
+    }
+
+    /// Prefer the directories that systemd passes to a service through its environment variables,
+    /// as listed in [`ServiceDirs::with_systemd`]. The variables are read immediately, from the
+    /// [`environment`](Self::environment) if set, and each one that is set is treated like a
+    /// directory set with [`dir`](Self::dir).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, ServiceDirs};
+    ///
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .prefix("/opt/app")
+    ///     .systemd()
+    ///     .build();
+    ///
+    /// println!("data_dir = {}", dirs.data_dir());
+    /// // Outside of systemd: /opt/app/var/lib/app
+    /// // With `StateDirectory=app`: /var/lib/app
+    /// ```
+    #[must_use]
+    pub fn systemd(mut self) -> Self {
+        let var = |name: &str| {
+            let value = self.var(name)?.into_string().ok()?;
+            let first = value.split(':').next()?;
+            let path = Utf8Path::new(first);
+
+            path.is_absolute().then(|| path.to_owned())
+        };
+
+        let cache = var("CACHE_DIRECTORY");
+        let config = var("CONFIGURATION_DIRECTORY");
+        let state = var("STATE_DIRECTORY");
+        let runtime = var("RUNTIME_DIRECTORY");
+        let log = var("LOGS_DIRECTORY");
+
+        if let Some(cache) = cache {
+            self.dirs.push((DirectoryKind::Cache, cache));
+        }
+        if let Some(config) = config {
+            self.dirs.push((DirectoryKind::Config, config));
+        }
+        if let Some(state) = state {
+            self.dirs.push((DirectoryKind::Data, state.clone()));
+            self.dirs.push((DirectoryKind::State, state));
+        }
+        if let Some(runtime) = runtime {
+            self.dirs.push((DirectoryKind::Runtime, runtime));
+        }
+        if let Some(log) = log {
+            self.dirs.push((DirectoryKind::Log, log));
+        }
+
+        self
+    }
+
+    /// Shorthand for [`environment`](Self::environment) followed by [`systemd`](Self::systemd).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, MockEnv, ServiceDirs};
+    ///
+    /// let env = MockEnv::new().with_var("CACHE_DIRECTORY", "/var/cache/app");
+    /// let dirs = ServiceDirs::builder("example", "app")
+    ///     .root("/tmp/image")
+    ///     .systemd_in(&env)
+    ///     .build();
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!("/tmp/image/var/cache/app", dirs.cache_dir());
+    /// ```
+    #[must_use]
+    pub fn systemd_in(self, env: &dyn Environment) -> Self {
+        self.environment(env).systemd()
+    }
+
+    /// Construct the [`ServiceDirs`] with the configured settings.
+    #[must_use]
+    pub fn build(self) -> ServiceDirs {
+        let mut dirs = if self.platform == Platform::Windows {
+            self.windows_defaults()
+        } else {
+            self.unix_defaults()
+        };
+
+        for (kind, path) in self.dirs {
+            dirs.set_dir(kind, path);
+        }
+
+        if let Some(root) = &self.root {
+            dirs.reroot(self.platform, root);
+        }
+
+        dirs
+    }
 
-            #[cfg(not(any(unix, windows)))]
-            {
-                compile_error!("OS not supported")
+    fn unix_defaults(&self) -> ServiceDirs {
+        let platform = self.platform;
+        let application = self.application.as_ref();
+        let (etc, var, run, lib, share) = match &self.prefix {
+            Some(prefix) => (
+                platform.join(prefix.as_str(), &["etc"]),
+                platform.join(prefix.as_str(), &["var"]),
+                platform.join(prefix.as_str(), &["var/run"]),
+                platform.join(prefix.as_str(), &["lib"]),
+                vec![platform.join(prefix.as_str(), &["share"])],
+            ),
+            None => (
+                "/etc".into(),
+                "/var".into(),
+                "/run".into(),
+                "/usr/lib".into(),
+                vec!["/usr/local/share".into(), "/usr/share".into()],
+            ),
+        };
+        let data_dir = platform.join(var.as_str(), &["lib", application]);
+
+        ServiceDirs {
+            cache_dir: platform.join(var.as_str(), &["cache", application]),
+            config_dir: platform.join(etc.as_str(), &[application]),
+            data_dir: data_dir.clone(),
+            state_dir: Some(data_dir),
+            runtime_dir: Some(platform.join(run.as_str(), &[application])),
+            log_dir: Some(platform.join(var.as_str(), &["log", application])),
+            system_config_dirs: vec![platform.join(lib.as_str(), &[application])],
+            system_data_dirs: share
+                .iter()
+                .map(|share| platform.join(share.as_str(), &[application]))
+                .collect(),
+        }
+    }
+
+    fn windows_defaults(&self) -> ServiceDirs {
+        let (organization, application) = (self.organization.as_ref(), self.application.as_ref());
+        let program_data;
+        let base = match &self.prefix {
+            Some(prefix) => prefix.as_str(),
+            None if self.machine_wide => {
+                program_data = self.program_data();
+                program_data.as_str()
+            }
+            None => self.account.app_data_dir(),
+        };
+        // The machine-wide layout has no split between local and roaming data.
+        let dir = |folder: &str, name: &str| {
+            if self.machine_wide {
+                self.platform.join(base, &[organization, application, name])
+            } else {
+                self.platform
+                    .join(base, &[folder, organization, application, name])
             }
+        };
+
+        ServiceDirs {
+            cache_dir: dir("Local", "cache"),
+            config_dir: dir("Roaming", "config"),
+            data_dir: dir("Roaming", "data"),
+            state_dir: None,
+            runtime_dir: None,
+            log_dir: Some(dir("Local", "log")),
+            system_config_dirs: Vec::new(),
+            system_data_dirs: Vec::new(),
         }
+    }
+
+    /// Variables that are captured by [`environment`](Self::environment).
+    const VARS: [&'static str; 6] = [
+        "ProgramData",
+        "CACHE_DIRECTORY",
+        "CONFIGURATION_DIRECTORY",
+        "STATE_DIRECTORY",
+        "RUNTIME_DIRECTORY",
+        "LOGS_DIRECTORY",
+    ];
+
+    /// The value of a variable from the configured environment, or the current process if none was
+    /// set. Empty values are treated as unset.
+    fn var(&self, name: &str) -> Option<OsString> {
+        let env: &dyn Environment = match &self.env {
+            Some(snapshot) => snapshot,
+            None => &HostEnv,
+        };
+
+        env.var_os(name).filter(|value| !value.is_empty())
+    }
+
+    /// The location of the `%ProgramData%` folder, or its default if the variable is not set or not
+    /// valid UTF-8.
+    fn program_data(&self) -> String {
+        self.var("ProgramData")
+            .and_then(|value| value.into_string().ok())
+            .unwrap_or_else(|| "C:\\ProgramData".to_owned())
+    }
+}
+
+/// Windows accounts that services can run as, which decide the `AppData` folder of
+/// [`ServiceDirs`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum ServiceAccount {
+    /// Minimal privileges on the local machine and anonymous access to the network.
+    ///
+    /// `AppData` folder: C:\Windows\ServiceProfiles\LocalService\AppData
+    LocalService,
+    /// Minimal privileges on the local machine and access to the network as the computer.
+    ///
+    /// `AppData` folder: C:\Windows\ServiceProfiles\NetworkService\AppData
+    #[default]
+    NetworkService,
+    /// Extensive privileges on the local machine and access to the network as the computer.
+    ///
+    /// `AppData` folder: C:\Windows\System32\config\systemprofile\AppData
+    LocalSystem,
+}
 
-        inner(organization.as_ref(), application.as_ref())
+impl ServiceAccount {
+    /// The location of the account's `AppData` folder.
+    #[must_use]
+    pub const fn app_data_dir(self) -> &'static str {
+        match self {
+            Self::LocalService => "C:\\Windows\\ServiceProfiles\\LocalService\\AppData",
+            Self::NetworkService => "C:\\Windows\\ServiceProfiles\\NetworkService\\AppData",
+            Self::LocalSystem => "C:\\Windows\\System32\\config\\systemprofile\\AppData",
+        }
     }
 }
 
@@ -111,4 +658,44 @@ impl Directories for ServiceDirs {
     fn data_dir(&self) -> &Utf8Path {
         &self.data_dir
     }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        self.state_dir.as_deref()
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        self.log_dir.as_deref()
+    }
+
+    fn system_config_dirs(&self) -> &[Utf8PathBuf] {
+        &self.system_config_dirs
+    }
+
+    fn system_data_dirs(&self) -> &[Utf8PathBuf] {
+        &self.system_data_dirs
+    }
+
+    /// Service directories are commonly accessed by administrators or tools in the service's group
+    /// as well. Therefore, the config directory is readable by everyone and all other directories
+    /// by the group, except for the runtime directory.
+    fn dir_mode(&self, kind: DirectoryKind) -> u32 {
+        match kind {
+            DirectoryKind::Config => 0o755,
+            DirectoryKind::Cache
+            | DirectoryKind::Data
+            | DirectoryKind::State
+            | DirectoryKind::Log => 0o750,
+            DirectoryKind::Runtime => 0o700,
+        }
+    }
+
+    /// The config directory is commonly provisioned by a package or an administrator and only
+    /// read by the service, so it may be owned by `root`.
+    fn root_owned(&self, kind: DirectoryKind) -> bool {
+        kind == DirectoryKind::Config
+    }
 }
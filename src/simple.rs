@@ -1,6 +1,19 @@
-use std::env;
+use std::borrow::Cow;
 
-use crate::unified::UnifiedDirs;
+use crate::{
+    container::ContainerDirs,
+    detect,
+    heuristic::{self, ArgsConfig, EnvConfig},
+    local::LocalDirs,
+    overrides,
+    service::ServiceDirs,
+    trace::{ForcedBy, Outcome, Step, Trace},
+    unified::{Mode, UnifiedDirs},
+    Environment, Error, HostEnv,
+};
+
+/// Environment variable to force a specific mode at runtime.
+const MODE_VAR: &str = "UNIDIRS_MODE";
 
 /// The simple builder is constructed through the [`UnifiedDirs::simple`] method and allows to
 /// further configure ways of detecting whether the application is run as a service or by the user.
@@ -8,8 +21,18 @@ use crate::unified::UnifiedDirs;
 /// [`with`](Self::with) and all the `with_*` functions are called and evaluated in order and
 /// immediately (**not** delayed until the call to [`build`](Self::build)). If service mode is
 /// detected by any technique, further functions won't be evaluated anymore.
+///
+/// Each technique is recorded in a [`Trace`], which can be retrieved together with the directories
+/// through [`build_with_trace`](Self::build_with_trace).
 pub struct SimpleBuilder<Q, O, A> {
-    service: bool,
+    env: Box<dyn Environment>,
+    mode: Option<Mode>,
+    forced: Option<Mode>,
+    steps: Vec<Step>,
+    container: Option<ContainerDirs>,
+    overrides: Option<String>,
+    systemd_paths: bool,
+    discover_root: bool,
     qualifier: Q,
     organization: O,
     application: A,
@@ -23,28 +46,119 @@ where
 {
     pub(crate) fn new(qualifier: Q, organization: O, application: A) -> Self {
         Self {
-            service: false,
+            env: Box::new(HostEnv),
+            mode: None,
+            forced: None,
+            steps: Vec::new(),
+            container: None,
+            overrides: None,
+            systemd_paths: false,
+            discover_root: false,
             qualifier,
             organization,
             application,
         }
     }
 
+    /// Use the given environment instead of the process environment, for all techniques and the
+    /// construction of the directories. As the techniques are evaluated immediately, this must be
+    /// called before any of them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new().with_args(["app", "--service"]);
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_args()
+    ///     .force_mode(Mode::Service)
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Service), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn environment(mut self, env: impl Environment + 'static) -> Self {
+        self.env = Box::new(env);
+        self
+    }
+
+    /// The environment that is used for all techniques, which is useful for custom logic in
+    /// [`with`](Self::with).
+    #[must_use]
+    pub fn env(&self) -> &dyn Environment {
+        &*self.env
+    }
+
     /// Use certain environment variable names to detect to be in service mode. The value of each
     /// variable doesn't matter, just whether the variable is present.
     ///
-    /// Currently the name `SERVICE` and `DAEMON` indicate the service mode.
+    /// Currently the name `SERVICE` and `DAEMON` indicate the service mode. As these generic names
+    /// can easily clash with other software, prefer [`with_env_config`](Self::with_env_config).
     #[must_use]
     pub fn with_env(self) -> Self {
-        self.with(|_| env::vars_os().any(|(name, _)| name == "SERVICE" || name == "DAEMON"))
+        self.with_labeled("with_env", |builder| {
+            ["SERVICE", "DAEMON"]
+                .into_iter()
+                .any(|name| builder.env.var_os(name).is_some())
+        })
+    }
+
+    /// Use configurable environment variables to detect the service mode, or optionally the user
+    /// mode. See [`EnvConfig`] for details about the variable names and the accepted values.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{EnvConfig, MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "my-app")
+    ///     .environment(
+    ///         MockEnv::new()
+    ///             .with_var("MY_APP_SERVICE", "no")
+    ///             .with_current_dir("/app")
+    ///             .with_home_dir("/home/alice"),
+    ///     )
+    ///     .with_env_config(EnvConfig::new().force_user(true))
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::User), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_env_config(self, config: EnvConfig) -> Self {
+        self.detect("with_env_config".into(), |builder| {
+            config
+                .detect(&*builder.env, builder.application.as_ref())
+                .map(|service| if service { Mode::Service } else { Mode::User })
+        })
     }
 
     /// Use certain program arguments to detect to be in service mode.
     ///
-    /// Currently the arguments `--service` and `--daemon` indicate the service mode.
+    /// Currently the arguments `--service` and `--daemon` indicate the service mode. Every argument
+    /// is checked, including option values and anything after `--`, so
+    /// [`with_args_config`](Self::with_args_config) is the more precise alternative.
     #[must_use]
     pub fn with_args(self) -> Self {
-        self.with(|_| env::args_os().any(|name| name == "--service" || name == "--daemon"))
+        self.with_labeled("with_args", |builder| {
+            builder
+                .env
+                .args_os()
+                .iter()
+                .any(|name| name == "--service" || name == "--daemon")
+        })
+    }
+
+    /// Use configurable command line arguments to detect the mode. See [`ArgsConfig`] for details
+    /// about the supported syntax.
+    #[must_use]
+    pub fn with_args_config(self, config: ArgsConfig) -> Self {
+        self.detect("with_args_config".into(), |builder| {
+            config.detect(&*builder.env)
+        })
     }
 
     /// Compare the executing user's account name against the application name to detect the service
@@ -55,7 +169,387 @@ where
     /// [`UnifiedDirs::simple`] matches the executing username, it indicates the service mode.
     #[must_use]
     pub fn with_username(self) -> Self {
-        self.with(|builder| whoami::username_os() == builder.application.as_ref())
+        self.with_labeled("with_username", |builder| {
+            builder.env.username_os() == builder.application.as_ref()
+        })
+    }
+
+    /// Match the executing user's account name against a glob pattern to detect the service mode.
+    ///
+    /// This is a more flexible variant of [`with_username`](Self::with_username) for service
+    /// accounts that are named differently from the application. In the pattern, `*` matches any
+    /// number of characters, `?` matches a single character and `{app}` is replaced with the
+    /// application name passed in [`UnifiedDirs::simple`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::UnifiedDirs;
+    ///
+    /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .with_username_pattern("_{app}")
+    ///     .with_username_pattern("svc-{app}")
+    ///     .with_username_pattern("*-daemon")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[must_use]
+    pub fn with_username_pattern(self, pattern: impl AsRef<str>) -> Self {
+        self.with_labeled("with_username_pattern", |builder| {
+            let pattern = pattern
+                .as_ref()
+                .replace("{app}", builder.application.as_ref());
+            detect::glob_match(&pattern, &builder.env.username_os().to_string_lossy())
+        })
+    }
+
+    /// Detect whether the executing user is a system account, which is commonly used to run
+    /// services. This is the case if any of the following applies:
+    ///
+    /// - The user ID is in the range of system accounts, which ends at `SYS_UID_MAX` (or below
+    ///   `UID_MIN`) in `/etc/login.defs`. Without the file, IDs below 1000 (below 500 on macOS)
+    ///   are considered system accounts.
+    /// - The user ID is in the range that systemd uses for `DynamicUser=`.
+    /// - The account has a `nologin` or `false` shell, or its home directory doesn't exist.
+    ///
+    /// The `root` user is never considered, as it is commonly used interactively as well. This
+    /// technique is only supported on Unix systems.
+    ///
+    /// # Example
+    ///
+    /// Statically allocated accounts below `SYS_UID_MIN` are system accounts as well:
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_uid(48)
+    ///     .with_file(
+    ///         "/etc/login.defs",
+    ///         "UID_MIN 1000\nUID_MAX 60000\nSYS_UID_MIN 201\nSYS_UID_MAX 999\n",
+    ///     )
+    ///     .with_file(
+    ///         "/etc/passwd",
+    ///         "apache:x:48:48:Apache:/usr/share/httpd:/bin/bash\n",
+    ///     )
+    ///     .with_dir("/usr/share/httpd");
+    ///
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_system_account()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Service), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_system_account(self) -> Self {
+        self.detect("with_system_account".into(), |builder| {
+            detect::system_account(&*builder.env)
+        })
+    }
+
+    /// Detect whether the application was started by systemd, based on the variables it passes
+    /// to services (`INVOCATION_ID`, `JOURNAL_STREAM` and `NOTIFY_SOCKET`) and the cgroup the
+    /// process is placed in.
+    ///
+    /// A service unit of the system's service manager indicates the service mode. A unit of a
+    /// user's service manager (`systemd --user`) explicitly indicates the user mode instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_file("/proc/self/cgroup", "0::/system.slice/app.service\n");
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_systemd()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Service), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_systemd(self) -> Self {
+        self.detect("with_systemd".into(), |builder| {
+            detect::systemd(&*builder.env)
+        })
+    }
+
+    /// Detect the service mode by the absence of a controlling terminal, which is the case for
+    /// daemonized processes.
+    ///
+    /// This technique is only supported on Linux, as it relies on the `/proc` file system.
+    ///
+    /// # Example
+    ///
+    /// The process based techniques can be combined, to detect applications that were started in
+    /// the background without any further configuration:
+    ///
+    /// ```rust
+    /// use unidirs::UnifiedDirs;
+    ///
+    /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .with_no_tty()
+    ///     .with_null_stdin()
+    ///     .with_parent_process()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[must_use]
+    pub fn with_no_tty(self) -> Self {
+        self.detect("with_no_tty".into(), |builder| {
+            detect::no_tty(&*builder.env)
+        })
+    }
+
+    /// Detect the service mode by the standard input being redirected from `/dev/null`, as usually
+    /// done by service managers and daemonized processes.
+    ///
+    /// This technique is only supported on Linux, as it relies on the `/proc` file system.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_link("/proc/self/fd/0", "/dev/null");
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_null_stdin()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Service), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_null_stdin(self) -> Self {
+        self.detect("with_null_stdin".into(), |builder| {
+            detect::null_stdin(&*builder.env)
+        })
+    }
+
+    /// Detect the service mode by the parent process being either the init process (PID 1), like
+    /// the system's systemd instance, or a known process supervisor. Currently these are
+    /// `supervisord`, `runsv` (runit), `s6-supervise`, `tini`, `dumb-init` and `docker-init`.
+    ///
+    /// This technique is only supported on Linux, as it relies on the `/proc` file system.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_file("/proc/self/stat", "42 (app) S 7 42 42 0 -1 4194560")
+    ///     .with_file("/proc/7/comm", "supervisord\n");
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_parent_process()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Service), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_parent_process(self) -> Self {
+        self.detect("with_parent_process".into(), |builder| {
+            detect::parent_process(&*builder.env)
+        })
+    }
+
+    /// Detect whether the application runs inside a container, which selects the container mode.
+    ///
+    /// A container is detected by the presence of the `/.dockerenv` or `/run/.containerenv` files,
+    /// the `KUBERNETES_SERVICE_HOST` environment variable, a `container` environment variable set
+    /// to `docker`, `oci` or `podman`, or a container runtime in the cgroup of the init process.
+    /// System containers like LXC, which run their own init and service manager, are not
+    /// considered.
+    ///
+    /// The [`ContainerDirs`] default locations are used, unless configured through
+    /// [`container_dirs`](Self::container_dirs).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_file("/.dockerenv", "");
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_container()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Container), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_container(self) -> Self {
+        self.detect("with_container".into(), |builder| {
+            detect::container(&*builder.env)
+        })
+    }
+
+    /// Detect whether the application is run in portable mode, by checking for a marker file next
+    /// to the executable. The marker is either a `portable.txt` or `<app>.portable` file, with
+    /// `<app>` being the application name passed in [`UnifiedDirs::simple`].
+    ///
+    /// The portable mode selects the [`PortableDirs`](crate::PortableDirs) backend.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_current_exe("/opt/app/app")
+    ///     .with_file("/opt/app/portable.txt", "");
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_portable()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Portable), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_portable(self) -> Self {
+        self.detect("with_portable".into(), |builder| {
+            detect::portable(&*builder.env, builder.application.as_ref())
+        })
+    }
+
+    /// Detect whether the application is run in portable mode, by checking whether the directory
+    /// of the executable is writable by the current user and not located in a system location
+    /// like `/usr`, `/opt` or `C:\Program Files`.
+    ///
+    /// Executables located in a `bin` or `sbin` folder are never considered portable, as these are
+    /// common installation targets, even within the user's home directory (like `~/.local/bin`).
+    /// The same applies to the build output of cargo (like `target/release`).
+    /// Still, this technique is rather broad and should be combined with other techniques that
+    /// run before it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_current_dir("/app")
+    ///     .with_current_exe("/home/alice/apps/app/app")
+    ///     .with_writable_dir("/home/alice/apps/app");
+    /// let (_, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(env)
+    ///     .with_portable_writable()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(Mode::Portable), trace.steps()[0].outcome().mode());
+    /// ```
+    #[must_use]
+    pub fn with_portable_writable(self) -> Self {
+        self.detect("with_portable_writable".into(), |builder| {
+            detect::portable_writable(&*builder.env)
+        })
+    }
+
+    /// Use the given directories, in case the container mode is selected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{ContainerDirs, Directories, Mode, UnifiedDirs};
+    ///
+    /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .container_dirs(ContainerDirs::new_at("/volumes"))
+    ///     .force_mode(Mode::Container)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!("/volumes/data", dirs.data_dir());
+    /// ```
+    #[must_use]
+    pub fn container_dirs(mut self, dirs: ContainerDirs) -> Self {
+        self.container = Some(dirs);
+        self
+    }
+
+    /// Prefer the directories that systemd passes to a service through its environment variables,
+    /// in case the service mode is selected. See [`ServiceDirs::with_systemd`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(MockEnv::new().with_var("STATE_DIRECTORY", "/var/lib/private/app"))
+    ///     .systemd_paths()
+    ///     .force_mode(Mode::Service)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!("/var/lib/private/app", dirs.data_dir());
+    /// ```
+    #[must_use]
+    pub fn systemd_paths(mut self) -> Self {
+        self.systemd_paths = true;
+        self
+    }
+
+    /// Locate the [`LocalDirs`] in the project's root instead of the current working directory, in
+    /// case the local mode is selected. See [`LocalDirs::discover`] for details.
+    #[must_use]
+    pub fn discover_local_root(mut self) -> Self {
+        self.discover_root = true;
+        self
+    }
+
+    /// Allow to relocate single directories through environment variables, after the backend was
+    /// selected. The variables are named `<APP>_CACHE_DIR`, `<APP>_CONFIG_DIR` and so on, with
+    /// `<APP>` being derived from the application name. See [`OverrideDirs`](crate::OverrideDirs)
+    /// for details.
+    ///
+    /// The applied overrides are reported in the [`Trace`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, DirectoryKind, MockEnv, Mode, UnifiedDirs};
+    ///
+    /// let (dirs, trace) = UnifiedDirs::simple("com", "example", "my-app")
+    ///     .environment(MockEnv::new().with_var("MY_APP_LOG_DIR", "/tmp/log"))
+    ///     .env_overrides()
+    ///     .force_mode(Mode::Service)
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!("/tmp/log", dirs.log_dir().unwrap());
+    /// assert_eq!(DirectoryKind::Log, trace.overrides()[0].kind());
+    /// ```
+    #[must_use]
+    pub fn env_overrides(self) -> Self {
+        let prefix = heuristic::env_prefix(self.application.as_ref());
+        self.env_overrides_prefix(prefix)
+    }
+
+    /// Same as [`env_overrides`](Self::env_overrides), but use the given prefix for the variable
+    /// names as is.
+    #[must_use]
+    pub fn env_overrides_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.overrides = Some(prefix.into());
+        self
     }
 
     /// Define a custom detection logic for the service mode. A positive value means service mode, a
@@ -70,13 +564,15 @@ where
     /// detected to be in service mode due to the environment variable.
     ///
     /// ```rust
-    /// use std::env;
-    /// use unidirs::UnifiedDirs;
-    ///
-    /// env::set_var("SERVICE", "true");
+    /// use unidirs::{MockEnv, UnifiedDirs};
     ///
     /// let mut called = false;
     /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(
+    ///         MockEnv::new()
+    ///             .with_var("SERVICE", "true")
+    ///             .with_current_dir("/app"),
+    ///     )
     ///     .with_env()
     ///     .with(|_| {
     ///         called = true;
@@ -92,13 +588,15 @@ where
     /// the lambda as it is evaluated first:
     ///
     /// ```rust
-    /// use std::env;
-    /// use unidirs::UnifiedDirs;
-    ///
-    /// env::set_var("SERVICE", "true");
+    /// use unidirs::{MockEnv, UnifiedDirs};
     ///
     /// let mut called = false;
     /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .environment(
+    ///         MockEnv::new()
+    ///             .with_var("SERVICE", "true")
+    ///             .with_current_dir("/app"),
+    ///     )
     ///     .with(|_| {
     ///         called = true;
     ///         true
@@ -111,52 +609,201 @@ where
     /// ```
     #[must_use]
     pub fn with(self, f: impl FnOnce(&Self) -> bool) -> Self {
-        Self {
-            service: self.service || f(&self),
-            qualifier: self.qualifier,
-            organization: self.organization,
-            application: self.application,
+        self.with_labeled("with", f)
+    }
+
+    /// Same as [`with`](Self::with), but give the technique a label, under which it is recorded
+    /// in the [`Trace`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{Outcome, UnifiedDirs};
+    ///
+    /// let (dirs, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .with_labeled("no service", |_| false)
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// assert_eq!("no service", trace.steps()[0].label());
+    /// assert_eq!(Outcome::Undetected, trace.steps()[0].outcome());
+    /// ```
+    #[must_use]
+    pub fn with_labeled(
+        self,
+        label: impl Into<Cow<'static, str>>,
+        f: impl FnOnce(&Self) -> bool,
+    ) -> Self {
+        self.detect(label.into(), |builder| f(builder).then_some(Mode::Service))
+    }
+
+    /// Force the given mode, regardless of the build mode and any detection techniques. This takes
+    /// precedence over the `UNIDIRS_MODE` environment variable as well (see
+    /// [`build`](Self::build)).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::{Mode, UnifiedDirs};
+    ///
+    /// let dirs = UnifiedDirs::simple("com", "example", "app")
+    ///     .with(|_| false)
+    ///     .force_mode(Mode::Service)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Mode::Service, dirs.mode());
+    /// ```
+    #[must_use]
+    pub fn force_mode(mut self, mode: Mode) -> Self {
+        self.forced = Some(mode);
+        self
+    }
+
+    /// Construct the backend for the given mode.
+    fn backend(&self, mode: Mode) -> Result<UnifiedDirs, Error> {
+        let env = &*self.env;
+        let (qualifier, organization, application) = (
+            self.qualifier.as_ref(),
+            self.organization.as_ref(),
+            self.application.as_ref(),
+        );
+
+        match mode {
+            Mode::Local if self.discover_root => {
+                LocalDirs::discover_in::<_, &str>(env, []).map(UnifiedDirs::Local)
+            }
+            Mode::Local => UnifiedDirs::local_in(env),
+            Mode::Service if self.systemd_paths => Ok(UnifiedDirs::Service(
+                ServiceDirs::with_systemd_in(env, organization, application),
+            )),
+            Mode::Service => Ok(UnifiedDirs::Service(
+                ServiceDirs::builder(organization, application)
+                    .environment(env)
+                    .build(),
+            )),
+            Mode::User => UnifiedDirs::user_in(env, qualifier, organization, application),
+            Mode::Container => Ok(UnifiedDirs::Container(
+                self.container.clone().unwrap_or_default(),
+            )),
+            Mode::Portable => UnifiedDirs::portable_in(env),
         }
     }
 
+    /// Run a single detection technique, unless a mode was already detected, and record the
+    /// result.
+    fn detect(mut self, label: Cow<'static, str>, f: impl FnOnce(&Self) -> Option<Mode>) -> Self {
+        let outcome = if self.mode.is_some() {
+            Outcome::Skipped
+        } else {
+            self.mode = f(&self);
+            self.mode.map_or(Outcome::Undetected, Outcome::Detected)
+        };
+
+        self.steps.push(Step { label, outcome });
+        self
+    }
+
     /// Construct the [`UnifiedDirs`] instance with the backend decided by previously configured
     /// techniques.
     ///
+    /// - If a mode was set with [`force_mode`](Self::force_mode), it is used as is.
+    /// - Otherwise, if the `UNIDIRS_MODE` environment variable is set to either `local`, `user`,
+    ///   `service`, `container` or `portable`, the corresponding backend is used. An empty value
+    ///   is treated the same as an unset variable.
     /// - If the application was built in debug mode (or with `debug_assertions` enabled), it will
-    ///   always pick [`LocalDirs`](crate::LocalDirs).
+    ///   always pick [`LocalDirs`], located in the current working directory (or the project's
+    ///   root, if enabled with [`discover_local_root`](Self::discover_local_root)).
     /// - If any of the configured techniques detected that the application is run in service mode,
-    ///   the backend will be [`ServiceDirs`](crate::ServiceDirs).
+    ///   the backend will be [`ServiceDirs`], preferring any directories passed by systemd if
+    ///   enabled with [`systemd_paths`](Self::systemd_paths).
+    /// - If a technique detected a container, the backend will be [`ContainerDirs`].
+    /// - If a technique detected the portable mode, the backend will be
+    ///   [`PortableDirs`](crate::PortableDirs).
     /// - Otherwise, it'll be [`UserDirs`](crate::UserDirs).
-    #[must_use]
-    pub fn build(self) -> Option<UnifiedDirs> {
-        fn inner(
-            service: bool,
-            qualifier: &str,
-            organization: &str,
-            application: &str,
-        ) -> Option<UnifiedDirs> {
-            if cfg!(debug_assertions) {
-                UnifiedDirs::local()
-            } else if service {
-                Some(UnifiedDirs::service(organization, application))
-            } else {
-                UnifiedDirs::user(qualifier, organization, application)
-            }
+    ///
+    /// # Errors
+    ///
+    /// Fails if the `UNIDIRS_MODE` environment variable contains an invalid value, any enabled
+    /// directory override is not valid UTF-8 or not an absolute path, or the selected backend
+    /// can't be constructed. See [`LocalDirs::new`], [`UnifiedDirs::user`] and
+    /// [`UnifiedDirs::portable`] for details.
+    pub fn build(self) -> Result<UnifiedDirs, Error> {
+        self.build_with_trace().map(|(dirs, _)| dirs)
+    }
+
+    /// Same as [`build`](Self::build), but additionally return a [`Trace`] of all the evaluated
+    /// techniques and the resulting decision.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`build`](Self::build).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use unidirs::UnifiedDirs;
+    ///
+    /// let (dirs, trace) = UnifiedDirs::simple("com", "example", "app")
+    ///     .with_env()
+    ///     .with_args()
+    ///     .with_username()
+    ///     .build_with_trace()
+    ///     .unwrap();
+    ///
+    /// println!("selected directories:\n{trace}");
+    /// ```
+    pub fn build_with_trace(self) -> Result<(UnifiedDirs, Trace), Error> {
+        let forced_by = if let Some(mode) = self.forced {
+            Some((mode, ForcedBy::Builder))
+        } else if let Some(value) = self.env.var_os(MODE_VAR).filter(|value| !value.is_empty()) {
+            let mode = value
+                .to_str()
+                .ok_or_else(|| Error::InvalidMode(value.to_string_lossy().into_owned()))?
+                .parse()?;
+            Some((mode, ForcedBy::EnvVar))
+        } else {
+            None
+        };
+
+        let debug_override = forced_by.is_none() && cfg!(debug_assertions);
+        let mode = match forced_by {
+            Some((mode, _)) => mode,
+            None if debug_override => Mode::Local,
+            None => self.mode.unwrap_or(Mode::User),
+        };
+
+        let mut dirs = self.backend(mode)?;
+
+        let overrides = match self.overrides {
+            Some(prefix) => overrides::from_env(&*self.env, &prefix)?,
+            None => Vec::new(),
+        };
+        for o in &overrides {
+            dirs.set_dir(o.kind(), o.path().to_owned());
         }
 
-        inner(
-            self.service,
-            self.qualifier.as_ref(),
-            self.organization.as_ref(),
-            self.application.as_ref(),
-        )
+        Ok((
+            dirs,
+            Trace {
+                steps: self.steps,
+                forced_by: forced_by.map(|(_, by)| by),
+                debug_override,
+                mode,
+                overrides,
+            },
+        ))
     }
 
     /// Configure and execute the builder with all detection techniques enabled.
     ///
     /// This is a convenience shorthand for manually calling [`with_env`](Self::with_env),
-    /// [`with_args`](Self::with_args) and [`with_username`](Self::with_username) followed by
-    /// [`build`](Self::build).
+    /// [`with_args`](Self::with_args), [`with_username`](Self::with_username) and
+    /// [`with_systemd`](Self::with_systemd) followed by [`build`](Self::build).
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`build`](Self::build).
     ///
     /// # Example
     ///
@@ -171,9 +818,14 @@ where
     ///     .with_env()
     ///     .with_args()
     ///     .with_username()
+    ///     .with_systemd()
     ///     .build();
     /// ```
-    pub fn default(self) -> Option<UnifiedDirs> {
-        self.with_env().with_args().with_username().build()
+    pub fn default(self) -> Result<UnifiedDirs, Error> {
+        self.with_env()
+            .with_args()
+            .with_username()
+            .with_systemd()
+            .build()
     }
 }
@@ -0,0 +1,229 @@
+//! Helpers for tests of applications that use this crate.
+//!
+//! This module is only available with the `testing` feature enabled, which is best done in the
+//! `dev-dependencies` of a project:
+//!
+//! ```sh
+//! cargo add --dev unidirs --features testing
+//! ```
+
+use std::env;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use tempfile::TempDir;
+
+use crate::{
+    error, Directories, DirectoryKind, Error, LocalDirs, MockEnv, Platform, ServiceDirs,
+    UnifiedDirs, UserDirs,
+};
+
+/// Isolated directories in a fresh temporary folder, that is removed again once the value is
+/// dropped.
+///
+/// By default, the directories are laid out like [`LocalDirs`] directly in the temporary folder.
+/// Alternatively, the layout of the other backends can be simulated with [`TempDirs::service`]
+/// and [`TempDirs::user`], with the temporary folder taking the place of the file system root or
+/// the user's home directory respectively.
+///
+/// The directories themselves are not created, use [`Directories::create_all`] for that.
+///
+/// # Examples
+///
+/// ```rust
+/// use unidirs::{testing::TempDirs, Directories, UnifiedDirs};
+///
+/// fn code_under_test(dirs: &UnifiedDirs) {
+///     std::fs::write(dirs.data_dir().join("db.sqlite"), b"").unwrap();
+/// }
+///
+/// let temp = TempDirs::new().unwrap();
+/// temp.create_all().unwrap();
+///
+/// code_under_test(&temp.unified());
+///
+/// assert!(temp.data_dir().join("db.sqlite").exists());
+/// ```
+#[derive(Debug)]
+pub struct TempDirs {
+    temp: TempDir,
+    root: Utf8PathBuf,
+    dirs: UnifiedDirs,
+}
+
+impl TempDirs {
+    /// Create a new temporary folder with the [`LocalDirs`] layout.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the temporary folder can't be created or its path is not valid UTF-8.
+    pub fn new() -> Result<Self, Error> {
+        Self::with(|root| Ok(UnifiedDirs::Local(LocalDirs::new_at(root))))
+    }
+
+    /// Create a new temporary folder with the [`ServiceDirs`] layout of the current platform,
+    /// using the folder as file system root.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the temporary folder can't be created or its path is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{testing::TempDirs, DirectoryKind};
+    ///
+    /// let temp = TempDirs::service("example", "app").unwrap();
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!(Some("var/lib/app".into()), temp.relative(DirectoryKind::Data));
+    /// ```
+    pub fn service(
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::with(|root| {
+            Ok(UnifiedDirs::Service(
+                ServiceDirs::builder(organization, application)
+                    .root(root)
+                    .build(),
+            ))
+        })
+    }
+
+    /// Create a new temporary folder with the [`UserDirs`] layout of the current platform, using
+    /// the folder as the user's home directory. All environment variables are ignored, except for
+    /// the runtime directory, which is located in a `run` folder, the system-wide config
+    /// directory, which is located in an `etc/xdg` folder, and the system-wide data directories,
+    /// which are located in the `usr/local/share` and `usr/share` folders.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the temporary folder can't be created, its path is not valid UTF-8, or the
+    /// platform has no user directories.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{testing::TempDirs, DirectoryKind};
+    ///
+    /// let temp = TempDirs::user("com", "example", "app").unwrap();
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// assert_eq!(Some(".config/app".into()), temp.relative(DirectoryKind::Config));
+    /// ```
+    pub fn user(
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::with(|root| {
+            let platform = Platform::current();
+            let env = MockEnv::new()
+                .with_home_dir(root)
+                .with_var("XDG_RUNTIME_DIR", platform.join(root.as_str(), &["run"]))
+                .with_var(
+                    "XDG_CONFIG_DIRS",
+                    platform.join(root.as_str(), &["etc/xdg"]),
+                )
+                .with_var(
+                    "XDG_DATA_DIRS",
+                    format!(
+                        "{}:{}",
+                        platform.join(root.as_str(), &["usr/local/share"]),
+                        platform.join(root.as_str(), &["usr/share"]),
+                    ),
+                )
+                .with_var(
+                    "APPDATA",
+                    platform.join(root.as_str(), &["AppData", "Roaming"]),
+                )
+                .with_var(
+                    "LOCALAPPDATA",
+                    platform.join(root.as_str(), &["AppData", "Local"]),
+                );
+
+            UserDirs::for_platform(platform, &env, qualifier, organization, application)
+                .map(UnifiedDirs::User)
+        })
+    }
+
+    fn with(f: impl FnOnce(&Utf8Path) -> Result<UnifiedDirs, Error>) -> Result<Self, Error> {
+        let temp = TempDir::with_prefix("unidirs-").map_err(|source| Error::Io {
+            path: Utf8PathBuf::from_path_buf(env::temp_dir()).unwrap_or_default(),
+            source,
+        })?;
+        let root = error::utf8(temp.path())?.to_owned();
+        let dirs = f(&root)?;
+
+        Ok(Self { temp, root, dirs })
+    }
+
+    /// Keep the temporary folder after the value is dropped, for example to inspect the files
+    /// of a failing test.
+    #[must_use]
+    pub fn keep(mut self) -> Self {
+        self.temp.disable_cleanup(true);
+        self
+    }
+
+    /// The temporary folder that contains all directories.
+    #[must_use]
+    pub fn root(&self) -> &Utf8Path {
+        &self.root
+    }
+
+    /// The location of the directory of the given kind, relative to the temporary folder.
+    #[must_use]
+    pub fn relative(&self, kind: DirectoryKind) -> Option<&Utf8Path> {
+        self.dir(kind)?.strip_prefix(&self.root).ok()
+    }
+
+    /// A copy of the directories as [`UnifiedDirs`], to pass them to the code under test. The
+    /// directories are still removed once this value is dropped, so it must outlive the copy.
+    #[must_use]
+    pub fn unified(&self) -> UnifiedDirs {
+        self.dirs.clone()
+    }
+}
+
+impl Directories for TempDirs {
+    fn cache_dir(&self) -> &Utf8Path {
+        self.dirs.cache_dir()
+    }
+
+    fn config_dir(&self) -> &Utf8Path {
+        self.dirs.config_dir()
+    }
+
+    fn data_dir(&self) -> &Utf8Path {
+        self.dirs.data_dir()
+    }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        self.dirs.state_dir()
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.dirs.runtime_dir()
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        self.dirs.log_dir()
+    }
+
+    fn system_config_dirs(&self) -> &[Utf8PathBuf] {
+        self.dirs.system_config_dirs()
+    }
+
+    fn system_data_dirs(&self) -> &[Utf8PathBuf] {
+        self.dirs.system_data_dirs()
+    }
+
+    fn dir_mode(&self, kind: DirectoryKind) -> u32 {
+        self.dirs.dir_mode(kind)
+    }
+
+    fn root_owned(&self, kind: DirectoryKind) -> bool {
+        self.dirs.root_owned(kind)
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+use crate::{overrides::Override, unified::Mode};
+
+/// A record of how the [`SimpleBuilder`](crate::SimpleBuilder) decided on the backend of the
+/// [`UnifiedDirs`](crate::UnifiedDirs). It is returned by
+/// [`SimpleBuilder::build_with_trace`](crate::SimpleBuilder::build_with_trace) and meant to be
+/// logged at startup, to help finding out why a certain backend was picked.
+///
+/// The [`Display`] implementation renders each step on a separate line, followed by the final
+/// decision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trace {
+    pub(crate) steps: Vec<Step>,
+    pub(crate) forced_by: Option<ForcedBy>,
+    pub(crate) debug_override: bool,
+    pub(crate) mode: Mode,
+    pub(crate) overrides: Vec<Override>,
+}
+
+impl Trace {
+    /// All techniques that were configured on the builder, in the order they were added.
+    #[must_use]
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Whether the mode was forced, and if so, by what means. Forcing the mode takes precedence
+    /// over both the debug mode and the detection techniques.
+    #[must_use]
+    pub fn forced_by(&self) -> Option<ForcedBy> {
+        self.forced_by
+    }
+
+    /// Whether the techniques were ignored, because the application was built in debug mode.
+    #[must_use]
+    pub fn debug_override(&self) -> bool {
+        self.debug_override
+    }
+
+    /// The mode that was finally selected.
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Directories that were relocated through environment variables, if enabled with
+    /// [`SimpleBuilder::env_overrides`](crate::SimpleBuilder::env_overrides).
+    #[must_use]
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+
+        match self.forced_by {
+            Some(ForcedBy::Builder) => writeln!(f, "builder: forced {} mode", self.mode)?,
+            Some(ForcedBy::EnvVar) => writeln!(f, "UNIDIRS_MODE: forced {} mode", self.mode)?,
+            None => {}
+        }
+
+        if self.debug_override {
+            writeln!(f, "debug build: forced {} mode", Mode::Local)?;
+        }
+
+        write!(f, "selected {} mode", self.mode)?;
+
+        for o in &self.overrides {
+            write!(f, "\n{o}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The origin of a forced mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ForcedBy {
+    /// The mode was set through [`SimpleBuilder::force_mode`](crate::SimpleBuilder::force_mode).
+    Builder,
+    /// The mode was set through the `UNIDIRS_MODE` environment variable.
+    EnvVar,
+}
+
+/// A single detection technique that was configured on the builder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub(crate) label: Cow<'static, str>,
+    pub(crate) outcome: Outcome,
+}
+
+impl Step {
+    /// The name of the technique, like `with_env` for [`SimpleBuilder::with_env`], or the label
+    /// given to [`SimpleBuilder::with_labeled`].
+    ///
+    /// [`SimpleBuilder::with_env`]: crate::SimpleBuilder::with_env
+    /// [`SimpleBuilder::with_labeled`]: crate::SimpleBuilder::with_labeled
+    #[must_use]
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The result of the technique.
+    #[must_use]
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.label, self.outcome)
+    }
+}
+
+/// The result of a single detection technique.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome {
+    /// The technique wasn't evaluated, as a previous one already made a decision.
+    Skipped,
+    /// The technique was evaluated but didn't detect any specific mode.
+    Undetected,
+    /// The technique detected the given mode.
+    Detected(Mode),
+}
+
+impl Outcome {
+    /// The detected mode, if any.
+    #[must_use]
+    pub fn mode(self) -> Option<Mode> {
+        match self {
+            Self::Skipped | Self::Undetected => None,
+            Self::Detected(mode) => Some(mode),
+        }
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skipped => f.write_str("skipped"),
+            Self::Undetected => f.write_str("not detected"),
+            Self::Detected(mode) => write!(f, "detected {mode} mode"),
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use camino::Utf8Path;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
 
 use crate::{
-    local::LocalDirs, service::ServiceDirs, simple::SimpleBuilder, user::UserDirs, Directories,
+    container::ContainerDirs, local::LocalDirs, portable::PortableDirs, service::ServiceDirs,
+    simple::SimpleBuilder, user::UserDirs, Directories, DirectoryKind, Environment, Error,
 };
 
 /// Unified directories provide a common interface over all different ways of constructing directory
@@ -10,6 +16,7 @@ use crate::{
 /// The most significant function is [`UnifiedDirs::simple`], giving an automatic selector for the
 /// right directory provider based on the way an application is run.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
 pub enum UnifiedDirs {
     /// Local directories for development.
     Local(LocalDirs),
@@ -17,14 +24,81 @@ pub enum UnifiedDirs {
     Service(ServiceDirs),
     /// User specific application folders.
     User(UserDirs),
+    /// Directories for applications run inside a container.
+    Container(ContainerDirs),
+    /// Directories next to the application's executable.
+    Portable(PortableDirs),
+}
+
+/// The different modes an application can run in, each mapping to one of the [`UnifiedDirs`]
+/// variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Mode {
+    /// Local development, see [`LocalDirs`].
+    Local,
+    /// Run as a service, see [`ServiceDirs`].
+    Service,
+    /// Run by a regular user, see [`UserDirs`].
+    User,
+    /// Run inside a container, see [`ContainerDirs`].
+    Container,
+    /// Run from a self-contained folder, see [`PortableDirs`].
+    Portable,
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::Service => "service",
+            Self::User => "user",
+            Self::Container => "container",
+            Self::Portable => "portable",
+        })
+    }
+}
+
+impl FromStr for Mode {
+    type Err = Error;
+
+    /// Parse the mode from its lowercase name, ignoring the case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            Ok(Self::Local)
+        } else if s.eq_ignore_ascii_case("service") {
+            Ok(Self::Service)
+        } else if s.eq_ignore_ascii_case("user") {
+            Ok(Self::User)
+        } else if s.eq_ignore_ascii_case("container") {
+            Ok(Self::Container)
+        } else if s.eq_ignore_ascii_case("portable") {
+            Ok(Self::Portable)
+        } else {
+            Err(Error::InvalidMode(s.to_owned()))
+        }
+    }
 }
 
 impl UnifiedDirs {
     /// Shorthand to create unified dirs with [`LocalDirs`] as backend.
-    pub fn local() -> Option<Self> {
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`LocalDirs::new`].
+    pub fn local() -> Result<Self, Error> {
         LocalDirs::new().map(Self::Local)
     }
 
+    /// Shorthand to create unified dirs with [`LocalDirs`] as backend, in the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`LocalDirs::new_in`].
+    pub fn local_in(env: &dyn Environment) -> Result<Self, Error> {
+        LocalDirs::new_in(env).map(Self::Local)
+    }
+
     /// Shorthand to create [`LocalDirs`] backed unified dirs at a specific location.
     pub fn local_at(base: impl AsRef<Utf8Path>) -> Self {
         Self::Local(LocalDirs::new_at(base))
@@ -37,14 +111,56 @@ impl UnifiedDirs {
     }
 
     /// Shorthand to create unified dirs with [`UserDirs`] as backend.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`UserDirs::new`].
     pub fn user(
         qualifier: impl AsRef<str>,
         organization: impl AsRef<str>,
         application: impl AsRef<str>,
-    ) -> Option<Self> {
+    ) -> Result<Self, Error> {
         UserDirs::new(qualifier, organization, application).map(Self::User)
     }
 
+    /// Shorthand to create unified dirs with [`UserDirs`] as backend, in the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`UserDirs::new_in`].
+    pub fn user_in(
+        env: &dyn Environment,
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        UserDirs::new_in(env, qualifier, organization, application).map(Self::User)
+    }
+
+    /// Shorthand to create unified dirs with [`ContainerDirs`] as backend.
+    #[must_use]
+    pub fn container() -> Self {
+        Self::Container(ContainerDirs::new())
+    }
+
+    /// Shorthand to create unified dirs with [`PortableDirs`] as backend.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`PortableDirs::new`].
+    pub fn portable() -> Result<Self, Error> {
+        PortableDirs::new().map(Self::Portable)
+    }
+
+    /// Shorthand to create unified dirs with [`PortableDirs`] as backend, in the given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`PortableDirs::new_in`].
+    pub fn portable_in(env: &dyn Environment) -> Result<Self, Error> {
+        PortableDirs::new_in(env).map(Self::Portable)
+    }
+
     /// Create a builder for unified directories that uses various detection techniques to select
     /// the right backend. See the [`SimpleBuilder`] implementation for more details about the
     /// used techniques.
@@ -57,30 +173,90 @@ impl UnifiedDirs {
     {
         SimpleBuilder::new(qualifier, organization, application)
     }
-}
 
-impl Directories for UnifiedDirs {
-    fn cache_dir(&self) -> &Utf8Path {
+    /// The mode that corresponds to the backend in use.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Mode, UnifiedDirs};
+    ///
+    /// let dirs = UnifiedDirs::service("example", "app");
+    ///
+    /// assert_eq!(Mode::Service, dirs.mode());
+    /// ```
+    #[must_use]
+    pub fn mode(&self) -> Mode {
         match self {
-            Self::Local(dirs) => dirs.cache_dir(),
-            Self::Service(dirs) => dirs.cache_dir(),
-            Self::User(dirs) => dirs.cache_dir(),
+            Self::Local(_) => Mode::Local,
+            Self::Service(_) => Mode::Service,
+            Self::User(_) => Mode::User,
+            Self::Container(_) => Mode::Container,
+            Self::Portable(_) => Mode::Portable,
         }
     }
 
-    fn config_dir(&self) -> &Utf8Path {
+    /// Replace the location of a single directory in the backend.
+    pub(crate) fn set_dir(&mut self, kind: DirectoryKind, path: Utf8PathBuf) {
         match self {
-            Self::Local(dirs) => dirs.config_dir(),
-            Self::Service(dirs) => dirs.config_dir(),
-            Self::User(dirs) => dirs.config_dir(),
+            Self::Local(dirs) => dirs.set_dir(kind, path),
+            Self::Service(dirs) => dirs.set_dir(kind, path),
+            Self::User(dirs) => dirs.set_dir(kind, path),
+            Self::Container(dirs) => dirs.set_dir(kind, path),
+            Self::Portable(dirs) => dirs.set_dir(kind, path),
         }
     }
 
-    fn data_dir(&self) -> &Utf8Path {
+    /// Access the backend as trait object, to forward the [`Directories`] implementation.
+    fn backend(&self) -> &dyn Directories {
         match self {
-            Self::Local(dirs) => dirs.data_dir(),
-            Self::Service(dirs) => dirs.data_dir(),
-            Self::User(dirs) => dirs.data_dir(),
+            Self::Local(dirs) => dirs,
+            Self::Service(dirs) => dirs,
+            Self::User(dirs) => dirs,
+            Self::Container(dirs) => dirs,
+            Self::Portable(dirs) => dirs,
         }
     }
 }
+
+impl Directories for UnifiedDirs {
+    fn cache_dir(&self) -> &Utf8Path {
+        self.backend().cache_dir()
+    }
+
+    fn config_dir(&self) -> &Utf8Path {
+        self.backend().config_dir()
+    }
+
+    fn data_dir(&self) -> &Utf8Path {
+        self.backend().data_dir()
+    }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        self.backend().state_dir()
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.backend().runtime_dir()
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        self.backend().log_dir()
+    }
+
+    fn system_config_dirs(&self) -> &[Utf8PathBuf] {
+        self.backend().system_config_dirs()
+    }
+
+    fn system_data_dirs(&self) -> &[Utf8PathBuf] {
+        self.backend().system_data_dirs()
+    }
+
+    fn dir_mode(&self, kind: DirectoryKind) -> u32 {
+        self.backend().dir_mode(kind)
+    }
+
+    fn root_owned(&self, kind: DirectoryKind) -> bool {
+        self.backend().root_owned(kind)
+    }
+}
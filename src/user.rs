@@ -1,6 +1,13 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
 use camino::{Utf8Path, Utf8PathBuf};
 
-use crate::Directories;
+use crate::{
+    Directories, DirectoryKind, Environment, Error, HostEnv, KnownFolder, OsDirectories, Platform,
+};
 
 /// User directories are used when applications are directly run by local regular users. The folder
 /// locations vary greatly by platform, as each has their own rules about where to put them.
@@ -11,59 +18,213 @@ use crate::Directories;
 /// the common [XDG user directory](https://wiki.archlinux.org/title/XDG_user_directories)
 /// environment variables.
 ///
-/// | Type   | Location                                                 |
-/// | ------ | -------------------------------------------------------- |
-/// | Cache  | `$XDG_CACHE_HOME`/`<app>` or `$HOME`/.cache/`<app>`      |
-/// | Config | `$XDG_CONFIG_HOME`/`<app>` or `$HOME`/.config/`<app>`    |
-/// | Data   | `$XDG_DATA_HOME`/`<app>` or `$HOME`/.local/share/`<app>` |
+/// | Type    | Location                                                      |
+/// | ------- | ------------------------------------------------------------- |
+/// | Cache   | `$XDG_CACHE_HOME`/`<app>` or `$HOME`/.cache/`<app>`           |
+/// | Config  | `$XDG_CONFIG_HOME`/`<app>` or `$HOME`/.config/`<app>`         |
+/// | Data    | `$XDG_DATA_HOME`/`<app>` or `$HOME`/.local/share/`<app>`      |
+/// | State   | `$XDG_STATE_HOME`/`<app>` or `$HOME`/.local/state/`<app>`     |
+/// | Runtime | `$XDG_RUNTIME_DIR`/`<app>` or _none_ if the variable is unset |
+/// | Log     | _state_/log                                                   |
+///
+/// Additionally, system-wide configuration is searched in each of the `$XDG_CONFIG_DIRS`/`<app>`
+/// directories, or `/etc/xdg/<app>` if the variable is unset (see
+/// [`Directories::system_config_dirs`]). Read-only data is searched the same way in each of the
+/// `$XDG_DATA_DIRS`/`<app>` directories, or `/usr/local/share/<app>` and `/usr/share/<app>` if the
+/// variable is unset (see [`Directories::system_data_dirs`]).
 ///
 /// ## Mac OS
 ///
-/// | Type   | Location                                                     |
-/// | ------ | ------------------------------------------------------------ |
-/// | Cache  | `$HOME`/Library/Caches/`<qual>`.`<org>`.`<app>`              |
-/// | Config | `$HOME`/Library/Application Support/`<qual>`.`<org>`.`<app>` |
-/// | Data   | `$HOME`/Library/Application Support/`<qual>`.`<org>`.`<app>` |
+/// | Type    | Location                                                     |
+/// | ------- | ------------------------------------------------------------ |
+/// | Cache   | `$HOME`/Library/Caches/`<qual>`.`<org>`.`<app>`              |
+/// | Config  | `$HOME`/Library/Application Support/`<qual>`.`<org>`.`<app>` |
+/// | Data    | `$HOME`/Library/Application Support/`<qual>`.`<org>`.`<app>` |
+/// | State   | _none_                                                       |
+/// | Runtime | _none_                                                       |
+/// | Log     | `$HOME`/Library/Logs/`<qual>`.`<org>`.`<app>`                |
 ///
 /// ## Windows
 ///
-/// | Type   | Location                                    |
-/// | ------ | ------------------------------------------- |
-/// | Cache  | `%LOCALAPPDATA%`\\`<org>`\\`<app>`\cache    |
-/// | Config | `%ROAMINGAPPDATA%`\\`<org>`\\`<app>`\config |
-/// | Data   | `%ROAMINGAPPDATA%`\\`<org>`\\`<app>`\data   |
+/// | Type    | Location                                    |
+/// | ------- | ------------------------------------------- |
+/// | Cache   | `%LOCALAPPDATA%`\\`<org>`\\`<app>`\cache    |
+/// | Config  | `%ROAMINGAPPDATA%`\\`<org>`\\`<app>`\config |
+/// | Data    | `%ROAMINGAPPDATA%`\\`<org>`\\`<app>`\data   |
+/// | State   | _none_                                      |
+/// | Runtime | _none_                                      |
+/// | Log     | `%LOCALAPPDATA%`\\`<org>`\\`<app>`\log      |
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct UserDirs {
     cache_dir: Utf8PathBuf,
     config_dir: Utf8PathBuf,
     data_dir: Utf8PathBuf,
+    state_dir: Option<Utf8PathBuf>,
+    runtime_dir: Option<Utf8PathBuf>,
+    log_dir: Option<Utf8PathBuf>,
+    system_config_dirs: Vec<Utf8PathBuf>,
+    system_data_dirs: Vec<Utf8PathBuf>,
 }
 
 impl UserDirs {
     /// Create a new instance with the given qualifier, organization and application. These values
     /// are used to create a custom directory structure depending on the operating system standards.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the user's home directory can't be determined, any of the directories is not valid
+    /// UTF-8, or the platform has no user directories at all.
     pub fn new(
         qualifier: impl AsRef<str>,
         organization: impl AsRef<str>,
         application: impl AsRef<str>,
-    ) -> Option<Self> {
-        fn inner(qualifier: &str, organization: &str, application: &str) -> Option<UserDirs> {
-            directories::ProjectDirs::from(qualifier, organization, application)
-                .and_then(|dirs| UserDirs::from_project_dirs(&dirs).ok())
-        }
+    ) -> Result<Self, Error> {
+        Self::new_in(&HostEnv, qualifier, organization, application)
+    }
 
-        inner(
-            qualifier.as_ref(),
-            organization.as_ref(),
-            application.as_ref(),
+    /// Same as [`UserDirs::new`], but use the home directory, variables and known folders of the
+    /// given environment.
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`UserDirs::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, MockEnv, UserDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_home_dir("/home/alice")
+    ///     .with_var("APPDATA", "C:\\Users\\alice\\AppData\\Roaming")
+    ///     .with_var("LOCALAPPDATA", "C:\\Users\\alice\\AppData\\Local");
+    /// let dirs = UserDirs::new_in(&env, "com", "example", "app").unwrap();
+    ///
+    /// println!("data_dir = {}", dirs.data_dir());
+    /// ```
+    pub fn new_in(
+        env: &dyn Environment,
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::for_platform(
+            Platform::current(),
+            env,
+            qualifier,
+            organization,
+            application,
         )
     }
 
-    fn from_project_dirs(value: &directories::ProjectDirs) -> Result<Self, camino::FromPathError> {
+    /// Compute the directories for the given platform, based on the supplied environment instead
+    /// of the current process. The locations are the same as described above, independent of the
+    /// system this code runs on.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the environment lacks the home directory or, on Windows, the roaming and local
+    /// application data folders (see [`Environment::known_folder`]). Platforms without user
+    /// directories, like WebAssembly, always fail. Paths that are not valid UTF-8 are an error as
+    /// well, use [`OsUserDirs`] to support them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use unidirs::{Directories, Error, MockEnv, Platform, UserDirs};
+    ///
+    /// let env = MockEnv::new()
+    ///     .with_home_dir("/home/alice")
+    ///     .with_var("XDG_CONFIG_HOME", "/home/alice/conf")
+    ///     .with_var("XDG_CONFIG_DIRS", "/opt/xdg:/etc/xdg")
+    ///     .with_var("XDG_DATA_DIRS", "/opt/share");
+    /// let dirs_for =
+    ///     |platform| UserDirs::for_platform(platform, &env, "com", "Example", "My App");
+    ///
+    /// let dirs = dirs_for(Platform::Linux).unwrap();
+    /// assert_eq!("/home/alice/conf/myapp", dirs.config_dir());
+    /// assert_eq!(
+    ///     vec!["/home/alice/conf/myapp", "/opt/xdg/myapp", "/etc/xdg/myapp"],
+    ///     dirs.config_dirs(),
+    /// );
+    /// assert_eq!(
+    ///     vec!["/home/alice/.local/share/myapp", "/opt/share/myapp"],
+    ///     dirs.data_dirs(),
+    /// );
+    /// assert_eq!(Some("/home/alice/.local/state/myapp/log".into()), dirs.log_dir());
+    ///
+    /// let dirs = dirs_for(Platform::MacOs).unwrap();
+    /// assert_eq!(
+    ///     "/home/alice/Library/Application Support/com.Example.My-App",
+    ///     dirs.config_dir(),
+    /// );
+    ///
+    /// assert!(dirs_for(Platform::Unknown).is_err());
+    /// assert!(matches!(
+    ///     dirs_for(Platform::Windows),
+    ///     Err(Error::MissingEnvVar("APPDATA")),
+    /// ));
+    ///
+    /// let env = env
+    ///     .with_var("APPDATA", "C:\\Users\\alice\\AppData\\Roaming")
+    ///     .with_var("LOCALAPPDATA", "C:\\Users\\alice\\AppData\\Local");
+    /// let dirs = UserDirs::for_platform(Platform::Windows, &env, "com", "Example", "My App");
+    /// assert_eq!(
+    ///     "C:\\Users\\alice\\AppData\\Roaming\\Example\\My App\\config",
+    ///     dirs.unwrap().config_dir(),
+    /// );
+    /// ```
+    pub fn for_platform(
+        platform: Platform,
+        env: &dyn Environment,
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        OsUserDirs::for_platform(platform, env, qualifier, organization, application)
+            .and_then(Self::try_from)
+    }
+
+    /// Replace the location of a single directory.
+    pub(crate) fn set_dir(&mut self, kind: DirectoryKind, path: Utf8PathBuf) {
+        match kind {
+            DirectoryKind::Cache => self.cache_dir = path,
+            DirectoryKind::Config => self.config_dir = path,
+            DirectoryKind::Data => self.data_dir = path,
+            DirectoryKind::State => self.state_dir = Some(path),
+            DirectoryKind::Runtime => self.runtime_dir = Some(path),
+            DirectoryKind::Log => self.log_dir = Some(path),
+        }
+    }
+}
+
+impl TryFrom<OsUserDirs> for UserDirs {
+    type Error = Error;
+
+    /// Convert the directories into UTF-8 paths, failing on the first one that is not valid
+    /// UTF-8.
+    fn try_from(value: OsUserDirs) -> Result<Self, Self::Error> {
+        fn utf8(path: PathBuf) -> Result<Utf8PathBuf, Error> {
+            Utf8PathBuf::from_path_buf(path).map_err(Error::NonUtf8Path)
+        }
+
         Ok(Self {
-            cache_dir: <&Utf8Path>::try_from(value.cache_dir())?.to_owned(),
-            config_dir: <&Utf8Path>::try_from(value.config_dir())?.to_owned(),
-            data_dir: <&Utf8Path>::try_from(value.data_dir())?.to_owned(),
+            cache_dir: utf8(value.cache_dir)?,
+            config_dir: utf8(value.config_dir)?,
+            data_dir: utf8(value.data_dir)?,
+            state_dir: value.state_dir.map(utf8).transpose()?,
+            runtime_dir: value.runtime_dir.map(utf8).transpose()?,
+            log_dir: value.log_dir.map(utf8).transpose()?,
+            system_config_dirs: value
+                .system_config_dirs
+                .into_iter()
+                .map(utf8)
+                .collect::<Result<_, _>>()?,
+            system_data_dirs: value
+                .system_data_dirs
+                .into_iter()
+                .map(utf8)
+                .collect::<Result<_, _>>()?,
         })
     }
 }
@@ -80,4 +241,269 @@ impl Directories for UserDirs {
     fn data_dir(&self) -> &Utf8Path {
         &self.data_dir
     }
+
+    fn state_dir(&self) -> Option<&Utf8Path> {
+        self.state_dir.as_deref()
+    }
+
+    fn runtime_dir(&self) -> Option<&Utf8Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    fn log_dir(&self) -> Option<&Utf8Path> {
+        self.log_dir.as_deref()
+    }
+
+    fn system_config_dirs(&self) -> &[Utf8PathBuf] {
+        &self.system_config_dirs
+    }
+
+    fn system_data_dirs(&self) -> &[Utf8PathBuf] {
+        &self.system_data_dirs
+    }
+}
+
+/// The same directories as [`UserDirs`], but without the requirement of being valid UTF-8. This is
+/// a fallback for systems where the user's home directory, or any of the variables that the
+/// directories are based on, contains bytes that are not valid UTF-8.
+///
+/// # Examples
+///
+/// ```rust
+/// use unidirs::{Error, OsDirectories, OsUserDirs, UserDirs};
+///
+/// match UserDirs::new("com", "example", "app") {
+///     Ok(dirs) => println!("data_dir = {}", dirs.data_path().display()),
+///     Err(Error::NonUtf8Path(_)) => {
+///         let dirs = OsUserDirs::new("com", "example", "app").unwrap();
+///         println!("data_dir = {}", dirs.data_path().display());
+///     }
+///     Err(e) => panic!("{e}"),
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OsUserDirs {
+    cache_dir: PathBuf,
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    state_dir: Option<PathBuf>,
+    runtime_dir: Option<PathBuf>,
+    log_dir: Option<PathBuf>,
+    system_config_dirs: Vec<PathBuf>,
+    system_data_dirs: Vec<PathBuf>,
+}
+
+impl OsUserDirs {
+    /// Create a new instance like [`UserDirs::new`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the user's home directory can't be determined, or the platform has no user
+    /// directories at all.
+    pub fn new(
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::new_in(&HostEnv, qualifier, organization, application)
+    }
+
+    /// Create a new instance like [`UserDirs::new_in`].
+    ///
+    /// # Errors
+    ///
+    /// Fails for the same reasons as [`OsUserDirs::new`].
+    pub fn new_in(
+        env: &dyn Environment,
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        Self::for_platform(
+            Platform::current(),
+            env,
+            qualifier,
+            organization,
+            application,
+        )
+    }
+
+    /// Compute the directories for the given platform, like [`UserDirs::for_platform`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the environment lacks the home directory or, on Windows, the roaming and local
+    /// application data folders (see [`Environment::known_folder`]). Platforms without user
+    /// directories, like WebAssembly, always fail.
+    pub fn for_platform(
+        platform: Platform,
+        env: &dyn Environment,
+        qualifier: impl AsRef<str>,
+        organization: impl AsRef<str>,
+        application: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        fn inner(
+            platform: Platform,
+            env: &dyn Environment,
+            qualifier: &str,
+            organization: &str,
+            application: &str,
+        ) -> Result<OsUserDirs, Error> {
+            match platform {
+                Platform::MacOs => macos(platform, env, qualifier, organization, application),
+                Platform::Windows => windows(platform, env, organization, application),
+                _ if platform.is_xdg() => xdg(platform, env, application),
+                _ => Err(Error::UnsupportedPlatform(platform)),
+            }
+        }
+
+        inner(
+            platform,
+            env,
+            qualifier.as_ref(),
+            organization.as_ref(),
+            application.as_ref(),
+        )
+    }
+}
+
+fn xdg(platform: Platform, env: &dyn Environment, application: &str) -> Result<OsUserDirs, Error> {
+    let home = env.home_dir().ok_or(Error::HomeDirNotFound)?;
+    let project: String = application
+        .trim()
+        .to_lowercase()
+        .split_whitespace()
+        .collect();
+    let absolute = |var: &str| {
+        non_empty(env.var_os(var)).filter(|value| value.to_string_lossy().starts_with('/'))
+    };
+    let dir = |var: &str, default: &str| match absolute(var) {
+        Some(base) => platform.join_os(&base, &[&project]),
+        None => platform.join_os(home.as_os_str(), &[default, &project]),
+    };
+    let state_dir = dir("XDG_STATE_HOME", ".local/state");
+    let list = |var: &str, defaults: &[&str]| {
+        list(env, var, defaults)
+            .iter()
+            .map(|dir| platform.join(dir, &[&project]).into())
+            .collect()
+    };
+
+    Ok(OsUserDirs {
+        cache_dir: dir("XDG_CACHE_HOME", ".cache"),
+        config_dir: dir("XDG_CONFIG_HOME", ".config"),
+        data_dir: dir("XDG_DATA_HOME", ".local/share"),
+        runtime_dir: absolute("XDG_RUNTIME_DIR")
+            .map(|runtime| platform.join_os(&runtime, &[&project])),
+        log_dir: Some(platform.join_os(state_dir.as_os_str(), &["log"])),
+        state_dir: Some(state_dir),
+        system_config_dirs: list("XDG_CONFIG_DIRS", &["/etc/xdg"]),
+        system_data_dirs: list("XDG_DATA_DIRS", &["/usr/local/share", "/usr/share"]),
+    })
+}
+
+fn macos(
+    platform: Platform,
+    env: &dyn Environment,
+    qualifier: &str,
+    organization: &str,
+    application: &str,
+) -> Result<OsUserDirs, Error> {
+    let home = env.home_dir().ok_or(Error::HomeDirNotFound)?;
+    let home = home.as_os_str();
+    let bundle_id = [qualifier, organization, application]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| part.replace(' ', "-"))
+        .collect::<Vec<_>>()
+        .join(".");
+    let support = platform.join_os(home, &["Library/Application Support", &bundle_id]);
+
+    Ok(OsUserDirs {
+        cache_dir: platform.join_os(home, &["Library/Caches", &bundle_id]),
+        config_dir: support.clone(),
+        data_dir: support,
+        state_dir: None,
+        runtime_dir: None,
+        log_dir: Some(platform.join_os(home, &["Library/Logs", &bundle_id])),
+        system_config_dirs: Vec::new(),
+        system_data_dirs: Vec::new(),
+    })
+}
+
+fn windows(
+    platform: Platform,
+    env: &dyn Environment,
+    organization: &str,
+    application: &str,
+) -> Result<OsUserDirs, Error> {
+    let folder = |folder: KnownFolder| {
+        non_empty(env.known_folder(folder).map(PathBuf::into_os_string))
+            .ok_or(Error::MissingEnvVar(folder.var()))
+    };
+    let roaming = folder(KnownFolder::RoamingAppData)?;
+    let local = folder(KnownFolder::LocalAppData)?;
+
+    Ok(OsUserDirs {
+        cache_dir: platform.join_os(&local, &[organization, application, "cache"]),
+        config_dir: platform.join_os(&roaming, &[organization, application, "config"]),
+        data_dir: platform.join_os(&roaming, &[organization, application, "data"]),
+        state_dir: None,
+        runtime_dir: None,
+        log_dir: Some(platform.join_os(&local, &[organization, application, "log"])),
+        system_config_dirs: Vec::new(),
+        system_data_dirs: Vec::new(),
+    })
+}
+
+/// The value of an environment variable, unless it's empty.
+fn non_empty(value: Option<OsString>) -> Option<OsString> {
+    value.filter(|value| !value.is_empty())
+}
+
+/// The absolute entries of a colon-separated list in an environment variable, or the given
+/// defaults if there are none. Entries that are not valid UTF-8 are skipped.
+fn list(env: &dyn Environment, name: &str, defaults: &[&str]) -> Vec<String> {
+    let entries = non_empty(env.var_os(name))
+        .map(|value| {
+            value
+                .to_string_lossy()
+                .split(':')
+                .filter(|entry| entry.starts_with('/') && !entry.contains('\u{fffd}'))
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    if entries.is_empty() {
+        defaults.iter().map(|&entry| entry.to_owned()).collect()
+    } else {
+        entries
+    }
+}
+
+impl OsDirectories for OsUserDirs {
+    fn cache_path(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    fn config_path(&self) -> &Path {
+        &self.config_dir
+    }
+
+    fn data_path(&self) -> &Path {
+        &self.data_dir
+    }
+
+    fn state_path(&self) -> Option<&Path> {
+        self.state_dir.as_deref()
+    }
+
+    fn runtime_path(&self) -> Option<&Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    fn log_path(&self) -> Option<&Path> {
+        self.log_dir.as_deref()
+    }
 }